@@ -0,0 +1,391 @@
+use crate::error::Error;
+use crate::source::Source;
+use iscc::base58::encode;
+use rustfft::num_complex::Complex;
+use rustfft::FftPlanner;
+use std::f64::consts::PI;
+use std::fs::File;
+use std::io::{self, Cursor};
+use std::path::Path;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error as DecodeError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::{MediaSource, MediaSourceStream};
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+const HEAD_CID_AUDIO: u8 = 0x14;
+
+//chromaprint: 11025 Hz mono, 4096 sample frames with 2/3 overlap, chroma from 28 to 3520 Hz
+const SAMPLE_RATE: u32 = 11025;
+const FRAME_SIZE: usize = 4096;
+const FRAME_STEP: usize = FRAME_SIZE / 3;
+const MIN_FREQ: f64 = 28.0;
+const MAX_FREQ: f64 = 3520.0;
+const BANDS: usize = 12;
+
+//like chromaprint's fpcalc only the first two minutes are fingerprinted
+const MAX_SAMPLES: usize = 120 * SAMPLE_RATE as usize;
+
+//Content-ID-Audio of an audio file (WAV, AIFF, FLAC, MP3, Ogg Vorbis). The first two
+//minutes of audio are decoded to 11025 Hz mono, fingerprinted like chromaprint (32 bit per
+//frame) and the frame values are condensed with simhash: all of them, then the first quarter.
+pub fn content_id_audio(source: &Source, partial: bool) -> Result<String, Error> {
+    let samples = decode(source)?;
+    let features = chroma(&samples);
+    let fingerprint = fingerprint(&features);
+    // the codes of silent or very short tracks would all match each other
+    if fingerprint.is_empty() || features.iter().all(|bands| bands.iter().all(|&b| b == 0.0)) {
+        return Err(Error::Extraction(
+            source.name().to_string(),
+            "Error creating content_id_audio: no audible sound (silent or too short)".to_string(),
+        ));
+    }
+
+    let quarter = fingerprint.len().div_ceil(4);
+    let mut digest = vec![HEAD_CID_AUDIO | partial as u8];
+    digest.extend(&simhash(&fingerprint).to_be_bytes());
+    digest.extend(&simhash(&fingerprint[..quarter]).to_be_bytes());
+    Ok(encode(&digest))
+}
+
+//mono samples at SAMPLE_RATE in 16 bit range (chromaprint works on i16 input), at most
+//MAX_SAMPLES: packets are decoded and resampled one by one until there are enough
+fn decode(source: &Source) -> Result<Vec<f32>, Error> {
+    let fail = |cause: String| {
+        Error::Extraction(
+            source.name().to_string(),
+            format!("Error creating content_id_audio: {}", cause),
+        )
+    };
+    // symphonia takes ownership of its input, data in memory is copied
+    let input: Box<dyn MediaSource> = match source {
+        Source::File(path) => Box::new(File::open(path).map_err(|e| source.io(e))?),
+        Source::Memory(_, data) => Box::new(Cursor::new(data.to_vec())),
+    };
+    let stream = MediaSourceStream::new(input, Default::default());
+    let mut hint = Hint::new();
+    if let Some(extension) = Path::new(source.name())
+        .extension()
+        .and_then(|e| e.to_str())
+    {
+        hint.with_extension(extension);
+    }
+    let probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            stream,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .map_err(|e| fail(e.to_string()))?;
+    let mut format = probed.format;
+    let track = format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or_else(|| fail("no audio track".to_string()))?;
+    let track_id = track.id;
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .map_err(|e| fail(e.to_string()))?;
+
+    // the sample rate of the first packet is used for the whole track
+    let mut resampler: Option<Resampler> = None;
+    let mut mono = Vec::new();
+    let mut samples = Vec::new();
+    while samples.len() < MAX_SAMPLES {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(DecodeError::IoError(ref e)) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(fail(e.to_string())),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // skip damaged packets like other decoders do
+            Err(DecodeError::DecodeError(_)) => continue,
+            Err(e) => return Err(fail(e.to_string())),
+        };
+        let spec = *decoded.spec();
+        let channels = spec.channels.count().max(1);
+        let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+        buffer.copy_interleaved_ref(decoded);
+        mono.clear();
+        mono.extend(
+            buffer
+                .samples()
+                .chunks(channels)
+                .map(|frame| frame.iter().sum::<f32>() / channels as f32 * 32768.0),
+        );
+        resampler
+            .get_or_insert_with(|| Resampler::new(spec.rate))
+            .push(&mono, &mut samples);
+    }
+    if let Some(mut resampler) = resampler {
+        resampler.finish(&mut samples);
+    }
+    samples.truncate(MAX_SAMPLES);
+    Ok(samples)
+}
+
+//band limited resampling to SAMPLE_RATE (Hann windowed sinc) of a stream of samples.
+//The rate ratio is p/q, so output samples fall on q different phases between input
+//samples and the filter taps of each phase are computed once.
+struct Resampler {
+    p: u64,
+    q: u64,
+    reach: u64,
+    //2 * reach + 1 weights per phase, for the input samples around the output sample
+    taps: Vec<Vec<f64>>,
+    //input not needed anymore is dropped, `input[0]` is input sample `offset`
+    input: Vec<f32>,
+    offset: u64,
+    total: u64,
+    //index of the next output sample
+    next: u64,
+}
+
+impl Resampler {
+    fn new(rate: u32) -> Resampler {
+        const HALF_WIDTH: f64 = 16.0;
+        const CUTOFF: f64 = 0.9;
+        let rate = rate.max(1);
+        let divisor = gcd(rate, SAMPLE_RATE);
+        let (p, q) = (rate / divisor, SAMPLE_RATE / divisor);
+        // low-pass below the new Nyquist frequency when downsampling
+        let scale = (f64::from(rate) / f64::from(SAMPLE_RATE)).max(1.0);
+        let reach = (HALF_WIDTH * scale).ceil() as u64;
+        let taps = (0..q)
+            .map(|phase| {
+                let fraction = f64::from(phase) / f64::from(q);
+                (0..=2 * reach)
+                    .map(|i| {
+                        let x = (i as f64 - reach as f64 - fraction) / scale;
+                        if x.abs() >= HALF_WIDTH {
+                            return 0.0;
+                        }
+                        let window = 0.5 + 0.5 * (PI * x / HALF_WIDTH).cos();
+                        sinc(x * CUTOFF) * window
+                    })
+                    .collect()
+            })
+            .collect();
+        Resampler {
+            p: u64::from(p),
+            q: u64::from(q),
+            reach,
+            taps,
+            input: Vec::new(),
+            offset: 0,
+            total: 0,
+            next: 0,
+        }
+    }
+
+    //add input and write the output samples that have all of their input
+    fn push(&mut self, samples: &[f32], output: &mut Vec<f32>) {
+        self.input.extend(samples);
+        self.total += samples.len() as u64;
+        self.run(output, false);
+    }
+
+    //write the remaining output samples at the end of the input
+    fn finish(&mut self, output: &mut Vec<f32>) {
+        self.run(output, true);
+    }
+
+    fn run(&mut self, output: &mut Vec<f32>, end: bool) {
+        if self.p == self.q {
+            output.append(&mut self.input);
+            return;
+        }
+        let length = self.total * self.q / self.p;
+        loop {
+            let position = self.next * self.p;
+            let (center, phase) = (position / self.q, position % self.q);
+            if (end && self.next >= length) || (!end && center + self.reach >= self.total) {
+                break;
+            }
+            let first = center.saturating_sub(self.reach);
+            let last = (center + self.reach).min(self.total - 1);
+            let taps = &self.taps[phase as usize];
+            let (mut sum, mut weights) = (0.0, 0.0);
+            for k in first..=last {
+                let weight = taps[(k + self.reach - center) as usize];
+                sum += f64::from(self.input[(k - self.offset) as usize]) * weight;
+                weights += weight;
+            }
+            output.push(if weights == 0.0 {
+                0.0
+            } else {
+                (sum / weights) as f32
+            });
+            self.next += 1;
+        }
+        // the next output sample starts at its center - reach
+        let needed = (self.next * self.p / self.q).saturating_sub(self.reach);
+        if needed > self.offset {
+            let drop = ((needed - self.offset) as usize).min(self.input.len());
+            self.input.drain(..drop);
+            self.offset += drop as u64;
+        }
+    }
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+fn sinc(x: f64) -> f64 {
+    if x == 0.0 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+//smoothed and normalized 12 band chroma vector per frame
+fn chroma(samples: &[f32]) -> Vec<[f64; BANDS]> {
+    let to_index = |freq: f64| (FRAME_SIZE as f64 * freq / f64::from(SAMPLE_RATE)).round() as usize;
+    let notes: Vec<(usize, usize)> = (to_index(MIN_FREQ)..to_index(MAX_FREQ))
+        .map(|i| {
+            let freq = i as f64 * f64::from(SAMPLE_RATE) / FRAME_SIZE as f64;
+            let octave = (freq / (440.0 / 16.0)).log2();
+            (i, (BANDS as f64 * (octave - octave.floor())) as usize)
+        })
+        .collect();
+    let window: Vec<f64> = (0..FRAME_SIZE)
+        .map(|i| 0.54 - 0.46 * (2.0 * PI * i as f64 / (FRAME_SIZE - 1) as f64).cos())
+        .collect();
+    let fft = FftPlanner::new().plan_fft_forward(FRAME_SIZE);
+
+    let mut frames = Vec::new();
+    let mut start = 0;
+    while start + FRAME_SIZE <= samples.len() {
+        let mut buffer: Vec<Complex<f64>> = samples[start..start + FRAME_SIZE]
+            .iter()
+            .zip(&window)
+            .map(|(s, w)| Complex::new(f64::from(*s) * w, 0.0))
+            .collect();
+        fft.process(&mut buffer);
+        let mut bands = [0.0; BANDS];
+        for &(i, note) in &notes {
+            bands[note] += buffer[i].norm_sqr();
+        }
+        frames.push(bands);
+        start += FRAME_STEP;
+    }
+
+    // chromaprint's chroma filter followed by euclidean normalization
+    const SMOOTHING: [f64; 5] = [0.25, 0.75, 1.0, 0.75, 0.25];
+    frames
+        .windows(SMOOTHING.len())
+        .map(|window| {
+            let mut bands = [0.0; BANDS];
+            for (frame, k) in window.iter().zip(&SMOOTHING) {
+                for (band, value) in bands.iter_mut().zip(frame) {
+                    *band += value * k;
+                }
+            }
+            let norm = bands.iter().map(|b| b * b).sum::<f64>().sqrt();
+            if norm < 0.01 {
+                [0.0; BANDS]
+            } else {
+                bands.map(|b| b / norm)
+            }
+        })
+        .collect()
+}
+
+//(filter type, first band, bands, frames) and quantizer thresholds of the classifiers
+//of chromaprint's default fingerprint algorithm
+const CLASSIFIERS: [(u8, usize, usize, usize, [f64; 3]); 16] = [
+    (0, 4, 3, 15, [1.98215, 2.35817, 2.63523]),
+    (4, 4, 6, 15, [-1.03809, -0.651211, -0.282167]),
+    (1, 0, 4, 16, [-0.298702, 0.119262, 0.558497]),
+    (3, 8, 2, 12, [-0.105439, 0.0153946, 0.135898]),
+    (3, 4, 4, 8, [-0.142891, 0.0258736, 0.200632]),
+    (4, 0, 3, 5, [-0.826319, -0.590612, -0.368214]),
+    (1, 2, 2, 9, [-0.557409, -0.233035, 0.0534525]),
+    (2, 7, 3, 4, [-0.0646826, 0.00620476, 0.0784847]),
+    (2, 6, 2, 16, [-0.192387, -0.029699, 0.215855]),
+    (2, 1, 3, 2, [-0.0397818, -0.00568076, 0.0292026]),
+    (5, 10, 1, 15, [-0.53823, -0.369934, -0.190235]),
+    (3, 6, 2, 10, [-0.124877, 0.0296483, 0.139239]),
+    (2, 1, 1, 14, [-0.101475, 0.0225617, 0.231971]),
+    (3, 5, 6, 4, [-0.0799915, -0.00729616, 0.063262]),
+    (1, 9, 2, 12, [-0.272556, 0.019424, 0.302559]),
+    (3, 4, 2, 14, [-0.164292, -0.0321188, 0.0846339]),
+];
+
+//one 32 bit value per frame: 2 bit (gray coded) per classifier
+fn fingerprint(features: &[[f64; BANDS]]) -> Vec<u32> {
+    // integral image: sums[t][b] = sum of features[..t][..b]
+    let mut sums = vec![[0.0; BANDS + 1]; features.len() + 1];
+    for (t, frame) in features.iter().enumerate() {
+        for b in 0..BANDS {
+            sums[t + 1][b + 1] = frame[b] + sums[t][b + 1] + sums[t + 1][b] - sums[t][b];
+        }
+    }
+    let area = |t0: usize, b0: usize, t1: usize, b1: usize| {
+        sums[t1][b1] - sums[t0][b1] - sums[t1][b0] + sums[t0][b0]
+    };
+    let compare = |a: f64, b: f64| ((1.0 + a) / (1.0 + b)).ln();
+
+    let width = CLASSIFIERS.iter().map(|c| c.3).max().unwrap_or(1);
+    (0..(features.len() + 1).saturating_sub(width))
+        .map(|x| {
+            CLASSIFIERS
+                .iter()
+                .fold(0, |bits, &(kind, y, h, w, thresholds)| {
+                    let value = match kind {
+                        0 => compare(area(x, y, x + w, y + h), 0.0),
+                        1 => compare(
+                            area(x, y + h / 2, x + w, y + h),
+                            area(x, y, x + w, y + h / 2),
+                        ),
+                        2 => compare(
+                            area(x + w / 2, y, x + w, y + h),
+                            area(x, y, x + w / 2, y + h),
+                        ),
+                        3 => compare(
+                            area(x, y + h / 2, x + w / 2, y + h)
+                                + area(x + w / 2, y, x + w, y + h / 2),
+                            area(x, y, x + w / 2, y + h / 2)
+                                + area(x + w / 2, y + h / 2, x + w, y + h),
+                        ),
+                        4 => compare(
+                            area(x, y + h / 3, x + w, y + 2 * (h / 3)),
+                            area(x, y, x + w, y + h / 3) + area(x, y + 2 * (h / 3), x + w, y + h),
+                        ),
+                        _ => compare(
+                            area(x + w / 3, y, x + 2 * (w / 3), y + h),
+                            area(x, y, x + w / 3, y + h) + area(x + 2 * (w / 3), y, x + w, y + h),
+                        ),
+                    };
+                    let level = thresholds.iter().filter(|&&t| value >= t).count();
+                    (bits << 2) | [0, 1, 3, 2][level]
+                })
+        })
+        .collect()
+}
+
+//bitwise majority vote over the frame values
+fn simhash(values: &[u32]) -> u32 {
+    (0..32).fold(0, |hash, bit| {
+        let count = values.iter().filter(|&&v| v >> bit & 1 == 1).count();
+        if !values.is_empty() && 2 * count >= values.len() {
+            hash | 1 << bit
+        } else {
+            hash
+        }
+    })
+}
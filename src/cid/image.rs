@@ -0,0 +1,124 @@
+use crate::error::Error;
+use crate::source::Source;
+use ::image::metadata::Orientation;
+use ::image::{DynamicImage, ImageDecoder, ImageReader};
+use iscc::base58::encode;
+use iscc::cid_image::image_hash;
+use std::io::BufReader;
+
+const HEAD_CID_IMAGE: u8 = 0x12;
+const SIZE: usize = 32;
+
+//Content-ID-Image of an image file. The decoder is picked by content, EXIF orientation
+//is applied and the image is normalized the way the reference implementation (Pillow)
+//does: "L" grayscale conversion and a 32x32 bicubic resize.
+pub fn content_id_image(source: &Source, partial: bool) -> Result<String, Error> {
+    let fail = |cause: String| {
+        Error::Extraction(
+            source.name().to_string(),
+            format!("Error creating content_id_image: {}", cause),
+        )
+    };
+    let reader = ImageReader::new(BufReader::new(source.open()?))
+        .with_guessed_format()
+        .map_err(|e| source.io(e))?;
+    let mut decoder = reader.into_decoder().map_err(|e| fail(e.to_string()))?;
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+    let mut img = DynamicImage::from_decoder(decoder).map_err(|e| fail(e.to_string()))?;
+    img.apply_orientation(orientation);
+
+    let pixels = normalize(&img);
+    let mut digest = vec![HEAD_CID_IMAGE | partial as u8];
+    digest.extend(image_hash(&pixels));
+    Ok(encode(&digest))
+}
+
+//32x32 grayscale pixel rows
+pub(crate) fn normalize(img: &DynamicImage) -> Vec<Vec<u8>> {
+    let rgb = img.to_rgb8();
+    let (width, height) = (rgb.width() as usize, rgb.height() as usize);
+    // Pillow "L" conversion (ITU-R 601-2 luma in 16 bit fixed point)
+    let gray: Vec<u8> = rgb
+        .pixels()
+        .map(|p| {
+            let [r, g, b] = p.0;
+            ((u32::from(r) * 19595 + u32::from(g) * 38470 + u32::from(b) * 7471 + 0x8000) >> 16)
+                as u8
+        })
+        .collect();
+    let rows: Vec<&[u8]> = gray.chunks(width.max(1)).collect();
+
+    // horizontal pass on every row, then vertical pass on the result (like Pillow)
+    let horizontal = coefficients(width, SIZE);
+    let resized_rows: Vec<Vec<u8>> = rows
+        .iter()
+        .map(|row| {
+            horizontal
+                .iter()
+                .map(|(first, k)| convolve(k, |i| row[first + i]))
+                .collect()
+        })
+        .collect();
+    let vertical = coefficients(height, SIZE);
+    vertical
+        .iter()
+        .map(|(first, k)| {
+            (0..SIZE)
+                .map(|x| convolve(k, |i| resized_rows[first + i][x]))
+                .collect()
+        })
+        .collect()
+}
+
+//fixed point precision of the filter coefficients (Pillow: 32 - 8 - 2)
+const PRECISION_BITS: u32 = 22;
+
+fn convolve(k: &[i64], pixel: impl Fn(usize) -> u8) -> u8 {
+    let sum = k
+        .iter()
+        .enumerate()
+        .fold(1 << (PRECISION_BITS - 1), |sum, (i, k)| {
+            sum + i64::from(pixel(i)) * k
+        });
+    (sum >> PRECISION_BITS).clamp(0, 255) as u8
+}
+
+fn bicubic(x: f64) -> f64 {
+    let a = -0.5;
+    let x = x.abs();
+    if x < 1.0 {
+        ((a + 2.0) * x - (a + 3.0)) * x * x + 1.0
+    } else if x < 2.0 {
+        (((x - 5.0) * x + 8.0) * x - 4.0) * a
+    } else {
+        0.0
+    }
+}
+
+//first input pixel and fixed point filter coefficients for each output pixel
+fn coefficients(in_size: usize, out_size: usize) -> Vec<(usize, Vec<i64>)> {
+    let scale = in_size as f64 / out_size as f64;
+    // the filter is widened when downscaling (antialiasing)
+    let filterscale = scale.max(1.0);
+    let support = 2.0 * filterscale;
+    (0..out_size)
+        .map(|out| {
+            let center = (out as f64 + 0.5) * scale;
+            let first = ((center - support + 0.5) as i64).max(0) as usize;
+            let last = ((center + support + 0.5) as i64).min(in_size as i64) as usize;
+            let weights: Vec<f64> = (first..last)
+                .map(|x| bicubic((x as f64 - center + 0.5) / filterscale))
+                .collect();
+            let total: f64 = weights.iter().sum();
+            let k = weights
+                .iter()
+                .map(|w| {
+                    let w = if total != 0.0 { w / total } else { *w };
+                    let w = w * f64::from(1 << PRECISION_BITS);
+                    (if w < 0.0 { w - 0.5 } else { w + 0.5 }) as i64
+                })
+                .collect();
+            (first, k)
+        })
+        .collect()
+}
@@ -0,0 +1,540 @@
+use super::image::normalize;
+use crate::error::Error;
+use crate::source::Source;
+use ::image::{DynamicImage, GrayImage, ImageFormat};
+use iscc::base58::encode;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+
+const HEAD_CID_VIDEO: u8 = 0x16;
+
+//frames per second used from decoded video (like the reference implementation)
+const SAMPLE_FPS: f64 = 5.0;
+//frame rate of streams without one (raw MJPEG), ffmpeg's default
+const DEFAULT_FPS: f64 = 25.0;
+//ternary values of an MPEG-7 frame signature (ffmpeg `signature` filter)
+const SIGNATURE_SIZE: usize = 380;
+
+//Content-ID-Video of a video file. Frame features are the frame signatures of an ffmpeg
+//`signature` sidecar (XML or binary) if one is given, otherwise the 32x32 grayscale frames
+//(see cid::image) of Y4M, MJPEG and AVI (MJPEG) video sampled with 5 fps.
+pub fn content_id_video(
+    source: &Source,
+    mimetype: &str,
+    frame_sig: Option<&str>,
+    partial: bool,
+) -> Result<String, Error> {
+    let fail = |cause: String| {
+        Error::Extraction(
+            source.name().to_string(),
+            format!("Error creating content_id_video: {}", cause),
+        )
+    };
+    let features = match (frame_sig, mimetype) {
+        (Some(sig), _) => signature_features(sig)?,
+        (None, "video/x-yuv4mpeg") => {
+            y4m_features(source.open()?).map_err(|e| io_error(source, e, fail))?
+        }
+        (None, "video/x-msvideo") => {
+            avi_features(source.open()?).map_err(|e| io_error(source, e, fail))?
+        }
+        (None, "video/x-motion-jpeg") => {
+            mjpeg_features(&source.read()?).map_err(|e| io_error(source, e, fail))?
+        }
+        _ => {
+            return Err(Error::UnsupportedMediaType(
+                source.name().to_string(),
+                mimetype.to_string(),
+            ))
+        }
+    };
+    if features.is_empty() {
+        return Err(fail("no frames found".to_string()));
+    }
+    let mut digest = vec![HEAD_CID_VIDEO | partial as u8];
+    digest.extend(&wta_hash(&features).to_be_bytes());
+    Ok(encode(&digest))
+}
+
+//read errors are I/O errors, malformed data is an extraction error
+fn io_error(source: &Source, e: io::Error, fail: impl Fn(String) -> Error) -> Error {
+    match e.kind() {
+        io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof => fail(e.to_string()),
+        _ => source.io(e),
+    }
+}
+
+fn invalid(cause: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, cause.to_string())
+}
+
+//64 bit winner-take-all hash of the column sums of the distinct frame features: each bit
+//tells which of two (pseudo randomly chosen) feature positions has the larger sum
+fn wta_hash(features: &[Vec<u8>]) -> u64 {
+    let frames: BTreeSet<&Vec<u8>> = features.iter().collect();
+    let size = features.iter().map(|f| f.len()).max().unwrap_or(0);
+    if size < 2 {
+        return 0;
+    }
+    let mut sums = vec![0u64; size];
+    for frame in frames {
+        for (sum, value) in sums.iter_mut().zip(frame) {
+            *sum += u64::from(*value);
+        }
+    }
+    // xorshift64 with a fixed seed, so the positions are the same for every video
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut next = |bound: usize| {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        (state % bound as u64) as usize
+    };
+    (0..64).fold(0, |hash, _| {
+        let a = next(size);
+        let b = (a + 1 + next(size - 1)) % size;
+        (hash << 1) | (sums[b] > sums[a]) as u64
+    })
+}
+
+//frame signatures of an ffmpeg `signature` filter output (format=xml or format=binary)
+fn signature_features(sig: &str) -> Result<Vec<Vec<u8>>, Error> {
+    let fail = |cause: String| {
+        Error::Extraction(
+            sig.to_string(),
+            format!("Error reading frame signature: {}", cause),
+        )
+    };
+    let data = fs::read(sig).map_err(|e| Error::Io(sig.to_string(), e))?;
+    let frames = if data.trim_ascii_start().starts_with(b"<") {
+        signature_xml(&data).map_err(fail)?
+    } else {
+        signature_binary(&data).ok_or_else(|| fail("truncated binary signature".to_string()))?
+    };
+    if let Some(frame) = frames.iter().find(|f| f.len() != SIGNATURE_SIZE) {
+        return Err(fail(format!(
+            "frame signature with {} instead of {} values",
+            frame.len(),
+            SIGNATURE_SIZE
+        )));
+    }
+    Ok(frames)
+}
+
+//<FrameSignature> elements of the MPEG-7 XML
+fn signature_xml(data: &[u8]) -> Result<Vec<Vec<u8>>, String> {
+    let mut reader = Reader::from_reader(data);
+    let mut buf = Vec::new();
+    let mut frames = Vec::new();
+    let mut in_signature = false;
+    loop {
+        match reader.read_event(&mut buf) {
+            Ok(Event::Start(e)) if e.local_name() == b"FrameSignature" => in_signature = true,
+            Ok(Event::End(e)) if e.local_name() == b"FrameSignature" => in_signature = false,
+            Ok(Event::Text(e)) if in_signature => {
+                let text = e.unescape_and_decode(&reader).map_err(|e| e.to_string())?;
+                let frame = text
+                    .split_whitespace()
+                    .map(|v| v.parse::<u8>().map_err(|e| format!("{}: {}", v, e)))
+                    .collect::<Result<Vec<u8>, String>>()?;
+                frames.push(frame);
+            }
+            Ok(Event::Eof) => break,
+            Err(e) => return Err(e.to_string()),
+            _ => {}
+        }
+        buf.clear();
+    }
+    Ok(frames)
+}
+
+//MSB first reader for the binary signature
+struct Bits<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl Bits<'_> {
+    fn read(&mut self, count: usize) -> Option<u32> {
+        let mut value = 0;
+        for _ in 0..count {
+            let byte = self.data.get(self.position / 8)?;
+            value = (value << 1) | u32::from(byte >> (7 - self.position % 8) & 1);
+            self.position += 1;
+        }
+        Some(value)
+    }
+
+    fn skip(&mut self, count: usize) {
+        self.position += count;
+    }
+}
+
+//fine signatures of ffmpeg's binary export: a header, the coarse signatures of the
+//segments and per frame 1+32 bit time, 8 bit confidence, 5 words and 76 bytes holding
+//5 ternary values each. Frames are read up to the end of the data.
+fn signature_binary(data: &[u8]) -> Option<Vec<Vec<u8>>> {
+    const FRAME_BITS: usize = 1 + 32 + 8 + 5 * 8 + SIGNATURE_SIZE / 5 * 8;
+    let mut bits = Bits { data, position: 0 };
+    // spatial region, pixel coordinates, start frame, number of frames,
+    // media time unit and spatial region times
+    bits.skip(32 + 1 + 32 + 16 + 16 + 32 + 32 + 16 + 1 + 32 + 32);
+    let segments = bits.read(32)? as usize;
+    bits.skip(segments * (32 + 32 + 1 + 32 + 32 + 5 * 243));
+    // compression flag
+    bits.skip(1);
+    let mut signatures = Vec::new();
+    while bits.position + FRAME_BITS <= data.len() * 8 {
+        bits.skip(1 + 32 + 8 + 5 * 8);
+        let mut signature = Vec::with_capacity(SIGNATURE_SIZE);
+        for _ in 0..SIGNATURE_SIZE / 5 {
+            let packed = bits.read(8)?;
+            for power in [81, 27, 9, 3, 1] {
+                signature.push((packed / power % 3) as u8);
+            }
+        }
+        signatures.push(signature);
+    }
+    Some(signatures)
+}
+
+//true for the frames kept when sampling a video with `fps` down to SAMPLE_FPS
+fn sampled(index: usize, fps: f64) -> bool {
+    if fps <= SAMPLE_FPS {
+        return true;
+    }
+    let slot = |i: usize| (i as f64 * SAMPLE_FPS / fps).floor();
+    index == 0 || slot(index) > slot(index - 1)
+}
+
+fn gray_features(width: u32, height: u32, luma: Vec<u8>) -> io::Result<Vec<u8>> {
+    let frame = GrayImage::from_raw(width, height, luma).ok_or_else(|| invalid("frame size"))?;
+    Ok(normalize(&DynamicImage::ImageLuma8(frame)).concat())
+}
+
+fn jpeg_features(data: &[u8]) -> io::Result<Vec<u8>> {
+    let frame =
+        ::image::load_from_memory_with_format(&with_huffman_tables(data), ImageFormat::Jpeg)
+            .map_err(|e| invalid(&e.to_string()))?;
+    Ok(normalize(&frame).concat())
+}
+
+//largest frame width and height of a YUV4MPEG2 stream (8K UHD is 7680x4320)
+const Y4M_MAX_SIZE: u32 = 16384;
+
+//luma planes of a YUV4MPEG2 stream
+fn y4m_features(input: impl Read) -> io::Result<Vec<Vec<u8>>> {
+    let mut reader = BufReader::new(input);
+    let mut header = String::new();
+    reader.read_line(&mut header)?;
+    let mut params = header.trim_end().split(' ');
+    if params.next() != Some("YUV4MPEG2") {
+        return Err(invalid("not a YUV4MPEG2 stream"));
+    }
+    let (mut width, mut height, mut fps, mut colorspace) = (0, 0, DEFAULT_FPS, "420jpeg");
+    for param in params.filter(|p| !p.is_empty()) {
+        let (tag, value) = param.split_at(1);
+        match tag {
+            "W" => width = value.parse().map_err(|_| invalid("width"))?,
+            "H" => height = value.parse().map_err(|_| invalid("height"))?,
+            "F" => {
+                let mut rate = value.split(':').map(|v| v.parse::<f64>().unwrap_or(0.0));
+                let (n, d) = (rate.next().unwrap_or(0.0), rate.next().unwrap_or(1.0));
+                if n > 0.0 && d > 0.0 {
+                    fps = n / d;
+                }
+            }
+            "C" => colorspace = value,
+            _ => {}
+        }
+    }
+    if width == 0 || height == 0 {
+        return Err(invalid("missing frame size"));
+    }
+    if width > Y4M_MAX_SIZE || height > Y4M_MAX_SIZE {
+        return Err(invalid("frame size too large"));
+    }
+    let (w, h) = (width as usize, height as usize);
+    let (cw, ch) = (w.div_ceil(2), h.div_ceil(2));
+    // C420p10, C444p12, ... carry the bit depth, C420jpeg, C420paldv, ... don't
+    let depth = match colorspace {
+        "mono16" => 16,
+        _ => colorspace
+            .rsplit('p')
+            .next()
+            .and_then(|d| d.parse().ok())
+            .unwrap_or(8),
+    };
+    if !(8..=16).contains(&depth) {
+        return Err(invalid("bit depth"));
+    }
+    let chroma = match colorspace.get(..3).unwrap_or(colorspace) {
+        "mon" => 0,
+        "444" if colorspace.starts_with("444alpha") => 3 * w * h,
+        "444" => 2 * w * h,
+        "422" => 2 * cw * h,
+        "411" => 2 * w.div_ceil(4) * h,
+        _ => 2 * cw * ch,
+    };
+    let sample = if depth > 8 { 2 } else { 1 };
+    let mut plane = Vec::new();
+    let mut rest = Vec::new();
+    let mut features = Vec::new();
+    let mut line = Vec::new();
+    for index in 0.. {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        if !line.starts_with(b"FRAME") {
+            return Err(invalid("missing FRAME header"));
+        }
+        read_frame(&mut reader, &mut plane, w * h * sample)?;
+        read_frame(&mut reader, &mut rest, chroma * sample)?;
+        if sampled(index, fps) {
+            let luma = if sample == 1 {
+                plane.clone()
+            } else {
+                // little endian samples, keep the upper 8 bits
+                plane
+                    .chunks(2)
+                    .map(|s| (u16::from_le_bytes([s[0], s[1]]) >> (depth - 8)) as u8)
+                    .collect()
+            };
+            features.push(gray_features(width, height, luma)?);
+        }
+    }
+    Ok(features)
+}
+
+//read `size` bytes into `buffer`, which only grows with the data actually read
+fn read_frame(reader: &mut impl Read, buffer: &mut Vec<u8>, size: usize) -> io::Result<()> {
+    buffer.clear();
+    reader.take(size as u64).read_to_end(buffer)?;
+    if buffer.len() < size {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(())
+}
+
+//JPEG frames of a raw MJPEG stream
+fn mjpeg_features(data: &[u8]) -> io::Result<Vec<Vec<u8>>> {
+    let mut starts: Vec<usize> = data
+        .windows(4)
+        .enumerate()
+        .filter(|(_, w)| w == b"\xff\xd9\xff\xd8")
+        .map(|(i, _)| i + 2)
+        .collect();
+    starts.insert(0, 0);
+    starts.push(data.len());
+    starts
+        .windows(2)
+        .enumerate()
+        .filter(|(index, _)| sampled(*index, DEFAULT_FPS))
+        .map(|(_, range)| jpeg_features(&data[range[0]..range[1]]))
+        .collect()
+}
+
+//JPEG frames of the first video stream of an AVI file (MJPEG codec)
+fn avi_features(mut file: impl Read + Seek) -> io::Result<Vec<Vec<u8>>> {
+    let length = file.seek(SeekFrom::End(0))?;
+    let mut avi = Avi::default();
+    // OpenDML files continue with further RIFF AVIX chunks
+    let mut position = 0;
+    while position + 12 <= length {
+        file.seek(SeekFrom::Start(position))?;
+        let (id, size) = chunk_header(&mut file)?;
+        let mut form = [0; 4];
+        file.read_exact(&mut form)?;
+        if &id != b"RIFF" || (&form != b"AVI " && &form != b"AVIX") {
+            if position == 0 {
+                return Err(invalid("not an AVI file"));
+            }
+            break;
+        }
+        let end = (position + 8 + u64::from(size)).min(length);
+        avi.walk(&mut file, position + 12, end, 0)?;
+        position = end + end % 2;
+    }
+    let fps = avi.fps.unwrap_or(DEFAULT_FPS);
+    let mut features = Vec::new();
+    for (index, &(offset, size)) in avi.frames.iter().enumerate() {
+        // frames of size 0 repeat the previous frame
+        if size == 0 || !sampled(index, fps) {
+            continue;
+        }
+        let mut data = vec![0; size as usize];
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut data)?;
+        if !data.starts_with(b"\xff\xd8") {
+            return Err(invalid("unsupported video codec (only MJPEG)"));
+        }
+        features.push(jpeg_features(&data)?);
+    }
+    Ok(features)
+}
+
+//LIST chunks nested deeper are rejected, real files nest three levels (RIFF, movi, rec)
+const AVI_MAX_DEPTH: usize = 32;
+
+#[derive(Default)]
+struct Avi {
+    fps: Option<f64>,
+    stream: Option<[u8; 2]>,
+    frames: Vec<(u64, u32)>,
+}
+
+impl Avi {
+    //collect the frame rate (avih) and the offset and size of each video frame (##dc/##db)
+    fn walk(
+        &mut self,
+        file: &mut (impl Read + Seek),
+        start: u64,
+        end: u64,
+        depth: usize,
+    ) -> io::Result<()> {
+        let mut position = start;
+        while position + 8 <= end {
+            file.seek(SeekFrom::Start(position))?;
+            let (id, size) = chunk_header(file)?;
+            let data = position + 8;
+            match &id {
+                b"LIST" => {
+                    let mut list = [0; 4];
+                    file.read_exact(&mut list)?;
+                    if matches!(&list, b"hdrl" | b"movi" | b"rec ") {
+                        if depth >= AVI_MAX_DEPTH {
+                            return Err(invalid("LIST chunks nested too deep"));
+                        }
+                        self.walk(file, data + 4, (data + u64::from(size)).min(end), depth + 1)?;
+                    }
+                }
+                b"avih" => {
+                    let mut micros = [0; 4];
+                    file.read_exact(&mut micros)?;
+                    let micros = u32::from_le_bytes(micros);
+                    if micros > 0 {
+                        self.fps = Some(1_000_000.0 / f64::from(micros));
+                    }
+                }
+                // the first stream with frame chunks is the video
+                [a, b, b'd', b'c'] | [a, b, b'd', b'b']
+                    if a.is_ascii_digit()
+                        && b.is_ascii_digit()
+                        && *self.stream.get_or_insert([*a, *b]) == [*a, *b] =>
+                {
+                    if data + u64::from(size) > end {
+                        return Err(invalid("frame chunk exceeds its list"));
+                    }
+                    self.frames.push((data, size));
+                }
+                _ => {}
+            }
+            position = data + u64::from(size) + u64::from(size % 2);
+        }
+        Ok(())
+    }
+}
+
+fn chunk_header(file: &mut impl Read) -> io::Result<([u8; 4], u32)> {
+    let mut header = [0; 8];
+    file.read_exact(&mut header)?;
+    let mut id = [0; 4];
+    id.copy_from_slice(&header[..4]);
+    Ok((
+        id,
+        u32::from_le_bytes([header[4], header[5], header[6], header[7]]),
+    ))
+}
+
+//MJPEG frames usually leave out the Huffman tables and rely on the standard tables
+//(ITU T.81, Annex K.3), insert them before the start of scan in that case
+fn with_huffman_tables(data: &[u8]) -> Vec<u8> {
+    let mut position = 2;
+    while position + 4 <= data.len() && data[position] == 0xff {
+        let marker = data[position + 1];
+        if marker == 0xc4 {
+            break;
+        }
+        if marker == 0xda {
+            let mut jpeg = data[..position].to_vec();
+            jpeg.extend(standard_huffman_tables());
+            jpeg.extend(&data[position..]);
+            return jpeg;
+        }
+        let length = usize::from(u16::from_be_bytes([data[position + 2], data[position + 3]]));
+        position += 2 + length;
+    }
+    data.to_vec()
+}
+
+fn standard_huffman_tables() -> Vec<u8> {
+    fn range(first: u8, last: u8) -> Vec<u8> {
+        (first..=last).collect()
+    }
+    let dc_values = range(0, 11);
+    let mut ac_luminance = vec![
+        0x01, 0x02, 0x03, 0x00, 0x04, 0x11, 0x05, 0x12, 0x21, 0x31, 0x41, 0x06, 0x13, 0x51, 0x61,
+        0x07, 0x22, 0x71, 0x14, 0x32, 0x81, 0x91, 0xa1, 0x08, 0x23, 0x42, 0xb1, 0xc1, 0x15, 0x52,
+        0xd1, 0xf0, 0x24, 0x33, 0x62, 0x72, 0x82, 0x09, 0x0a, 0x16, 0x17, 0x18, 0x19, 0x1a, 0x25,
+        0x26, 0x27, 0x28, 0x29, 0x2a,
+    ];
+    let mut ac_chrominance = vec![
+        0x00, 0x01, 0x02, 0x03, 0x11, 0x04, 0x05, 0x21, 0x31, 0x06, 0x12, 0x41, 0x51, 0x07, 0x61,
+        0x71, 0x13, 0x22, 0x32, 0x81, 0x08, 0x14, 0x42, 0x91, 0xa1, 0xb1, 0xc1, 0x09, 0x23, 0x33,
+        0x52, 0xf0, 0x15, 0x62, 0x72, 0xd1, 0x0a, 0x16, 0x24, 0x34, 0xe1, 0x25, 0xf1, 0x17, 0x18,
+        0x19, 0x1a, 0x26, 0x27, 0x28, 0x29, 0x2a,
+    ];
+    // both AC tables continue with runs of consecutive values
+    ac_luminance.extend(range(0x34, 0x3a));
+    ac_chrominance.extend(range(0x35, 0x3a));
+    for high in 4..=7 {
+        ac_luminance.extend(range(high << 4 | 3, high << 4 | 0xa));
+        ac_chrominance.extend(range(high << 4 | 3, high << 4 | 0xa));
+    }
+    ac_luminance.extend(range(0x83, 0x8a));
+    ac_chrominance.extend(range(0x82, 0x8a));
+    for high in 9..=0xd {
+        ac_luminance.extend(range(high << 4 | 2, high << 4 | 0xa));
+        ac_chrominance.extend(range(high << 4 | 2, high << 4 | 0xa));
+    }
+    ac_luminance.extend(range(0xe1, 0xea));
+    ac_luminance.extend(range(0xf1, 0xfa));
+    ac_chrominance.extend(range(0xe2, 0xea));
+    ac_chrominance.extend(range(0xf2, 0xfa));
+
+    let tables: [(u8, [u8; 16], &[u8]); 4] = [
+        (
+            0x00,
+            [0, 1, 5, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0],
+            &dc_values,
+        ),
+        (
+            0x01,
+            [0, 3, 1, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0],
+            &dc_values,
+        ),
+        (
+            0x10,
+            [0, 2, 1, 3, 3, 2, 4, 3, 5, 5, 4, 4, 0, 0, 1, 0x7d],
+            &ac_luminance,
+        ),
+        (
+            0x11,
+            [0, 2, 1, 2, 4, 4, 3, 4, 7, 5, 4, 4, 0, 1, 2, 0x77],
+            &ac_chrominance,
+        ),
+    ];
+    let mut segment = vec![0xff, 0xc4, 0, 0];
+    for (class, counts, values) in tables.iter() {
+        segment.push(*class);
+        segment.extend(counts);
+        segment.extend(*values);
+    }
+    let length = (segment.len() - 2) as u16;
+    segment[2..4].copy_from_slice(&length.to_be_bytes());
+    segment
+}
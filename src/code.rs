@@ -0,0 +1,291 @@
+//! Parsing, inspection and comparison of ISCC codes, as used by `iscc-cli sim` and `info`.
+use iscc::base58::decode;
+
+use crate::error::Error;
+
+//Base58-ISCC symbol table (see iscc::base58)
+const SYMBOLS: &str = "C23456789rB1ZEFGTtYiAaVvMmHUPWXKDNbcdefghLjkSnopRqsJuQwxyz";
+const COMPONENT_LENGTH: usize = 13;
+
+/// Type of an ISCC component, given by its header byte.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ComponentType {
+    /// Meta-ID (header `0x00`)
+    Meta,
+    /// Content-ID of a text (`0x10`)
+    ContentText,
+    /// Content-ID of an image (`0x12`)
+    ContentImage,
+    /// Content-ID of audio (`0x14`)
+    ContentAudio,
+    /// Content-ID of a video (`0x16`)
+    ContentVideo,
+    /// Content-ID of mixed content (`0x18`)
+    ContentMixed,
+    /// Data-ID (`0x20`)
+    Data,
+    /// Instance-ID (`0x30`)
+    Instance,
+}
+
+impl ComponentType {
+    /// Component type for a header byte, the partial content flag is ignored.
+    pub fn from_header(header: u8) -> Option<ComponentType> {
+        match header {
+            0x00 => Some(ComponentType::Meta),
+            0x10 | 0x11 => Some(ComponentType::ContentText),
+            0x12 | 0x13 => Some(ComponentType::ContentImage),
+            0x14 | 0x15 => Some(ComponentType::ContentAudio),
+            0x16 | 0x17 => Some(ComponentType::ContentVideo),
+            0x18 | 0x19 => Some(ComponentType::ContentMixed),
+            0x20 => Some(ComponentType::Data),
+            0x30 => Some(ComponentType::Instance),
+            _ => None,
+        }
+    }
+
+    /// True for the Content-ID types.
+    pub fn is_content(self) -> bool {
+        matches!(
+            self,
+            ComponentType::ContentText
+                | ComponentType::ContentImage
+                | ComponentType::ContentAudio
+                | ComponentType::ContentVideo
+                | ComponentType::ContentMixed
+        )
+    }
+
+    /// Name of the component type, e.g. `Content-ID-Image`.
+    pub fn name(self) -> &'static str {
+        match self {
+            ComponentType::Meta => "Meta-ID",
+            ComponentType::ContentText => "Content-ID-Text",
+            ComponentType::ContentImage => "Content-ID-Image",
+            ComponentType::ContentAudio => "Content-ID-Audio",
+            ComponentType::ContentVideo => "Content-ID-Video",
+            ComponentType::ContentMixed => "Content-ID-Mixed",
+            ComponentType::Data => "Data-ID",
+            ComponentType::Instance => "Instance-ID",
+        }
+    }
+}
+
+/// A decoded ISCC component.
+#[derive(Debug)]
+pub struct Component {
+    /// The 13 character Base58-ISCC code
+    pub code: String,
+    /// Header byte (component type and partial content flag)
+    pub header: u8,
+    /// Component type
+    pub ctype: ComponentType,
+    /// 64 bit digest
+    pub digest: [u8; 8],
+}
+
+impl Component {
+    /// Partial content flag, `None` for components other than Content-IDs.
+    pub fn partial(&self) -> Option<bool> {
+        if self.ctype.is_content() {
+            Some(self.header & 1 == 1)
+        } else {
+            None
+        }
+    }
+
+    /// Hex encoded digest.
+    pub fn digest_hex(&self) -> String {
+        self.digest.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn digest_num(&self) -> u64 {
+        u64::from_be_bytes(self.digest)
+    }
+}
+
+/// Decode a 13 character ISCC component code, the `ISCC:` prefix is optional.
+pub fn parse_component(code: &str) -> Result<Component, Error> {
+    let code = strip_prefix(code);
+    if let Some(c) = code.chars().find(|c| !SYMBOLS.contains(*c)) {
+        return Err(Error::InvalidIscc(format!(
+            "{} -- Invalid character '{}' in ISCC component",
+            code, c
+        )));
+    }
+    if code.len() != COMPONENT_LENGTH {
+        return Err(Error::InvalidIscc(format!(
+            "{} -- ISCC component must be {} characters long, not {}",
+            code,
+            COMPONENT_LENGTH,
+            code.len()
+        )));
+    }
+    // iscc::base58::decode panics on values out of range, check before decoding
+    if symbols_value(&code[..2]) > u128::from(u8::MAX)
+        || symbols_value(&code[2..]) > u128::from(u64::MAX)
+    {
+        return Err(Error::InvalidIscc(format!(
+            "{} -- Not a valid Base58-ISCC component",
+            code
+        )));
+    }
+    let raw = decode(code);
+    let header = raw[0];
+    let ctype = match ComponentType::from_header(header) {
+        Some(ctype) => ctype,
+        None => {
+            return Err(Error::InvalidIscc(format!(
+                "{} -- Unknown ISCC component header 0x{:02x}",
+                code, header
+            )))
+        }
+    };
+    let mut digest: [u8; 8] = [0; 8];
+    digest.copy_from_slice(&raw[1..9]);
+    Ok(Component {
+        code: code.to_string(),
+        header,
+        ctype,
+        digest,
+    })
+}
+
+/// Decode the components of an ISCC code (`mid-cid-did-iid`), the `ISCC:` prefix is
+/// optional. The number and order of the components is not checked.
+pub fn parse_code(code: &str) -> Result<Vec<Component>, Error> {
+    strip_prefix(code).split('-').map(parse_component).collect()
+}
+
+//code without surrounding whitespace and the case-insensitive "ISCC:" prefix
+fn strip_prefix(code: &str) -> &str {
+    let code = code.trim();
+    match code.get(..5) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("ISCC:") => &code[5..],
+        _ => code,
+    }
+}
+
+/// Components of a fully qualified ISCC code.
+#[derive(Debug)]
+pub struct IsccCode {
+    /// Meta-ID
+    pub mid: Component,
+    /// Content-ID, containers and data files have none
+    pub cid: Option<Component>,
+    /// Data-ID
+    pub did: Component,
+    /// Instance-ID
+    pub iid: Component,
+}
+
+/// True if the input looks like a full ISCC code (components joined by `-`) rather than
+/// a single component, with or without the `ISCC:` prefix.
+pub fn is_full_code(code: &str) -> bool {
+    strip_prefix(code).contains('-')
+}
+
+/// Decode a full ISCC code (with or without Content-ID) and check the order of its
+/// components.
+pub fn parse_full_code(code: &str) -> Result<IsccCode, Error> {
+    let mut components = parse_code(code)?;
+    if components.len() != 3 && components.len() != 4 {
+        return Err(Error::InvalidIscc(format!(
+            "{} -- Expected a full ISCC code with 3 or 4 components (mid-[cid-]did-iid), found {}",
+            code.trim(),
+            components.len()
+        )));
+    }
+    let iid = components.pop().unwrap();
+    let did = components.pop().unwrap();
+    let cid = match components.len() {
+        2 => components.pop(),
+        _ => None,
+    };
+    let mid = components.pop().unwrap();
+    if mid.ctype != ComponentType::Meta
+        || matches!(&cid, Some(cid) if !cid.ctype.is_content())
+        || did.ctype != ComponentType::Data
+        || iid.ctype != ComponentType::Instance
+    {
+        return Err(Error::InvalidIscc(format!(
+            "{} -- Components must be in the order Meta-ID, Content-ID (optional), Data-ID, Instance-ID",
+            code.trim()
+        )));
+    }
+    Ok(IsccCode { mid, cid, did, iid })
+}
+
+/// Hamming distance between the digests of two components of the same type.
+pub fn distance(a: &Component, b: &Component) -> Result<u32, Error> {
+    if a.ctype != b.ctype {
+        return Err(Error::InvalidIscc(format!(
+            "Cannot compare {} ({}) with {} ({})",
+            a.ctype.name(),
+            a.code,
+            b.ctype.name(),
+            b.code
+        )));
+    }
+    Ok((a.digest_num() ^ b.digest_num()).count_ones())
+}
+
+/// Similarity in percent for a hamming distance of 64 bit digests.
+pub fn similarity(distance: u32) -> f64 {
+    ((64.0 - f64::from(distance)) / 64.0) * 100.0
+}
+
+/// Max hamming distance (of 64 bits) for Content- and Data-IDs to count as similar.
+pub const SIMILARITY_THRESHOLD: u32 = 12;
+
+/// Overall result of comparing two full ISCC codes, see [`verdict`].
+#[derive(Debug, PartialEq)]
+pub enum Verdict {
+    /// The Instance-IDs match: the same data
+    Identical,
+    /// The Content-IDs match
+    SameContent,
+    /// Content- or Data-IDs are within [`SIMILARITY_THRESHOLD`]
+    Similar,
+    /// None of the above
+    Different,
+}
+
+impl Verdict {
+    /// Name of the verdict as printed by `iscc-cli sim`, e.g. `same content`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Verdict::Identical => "identical",
+            Verdict::SameContent => "same content",
+            Verdict::Similar => "similar",
+            Verdict::Different => "different",
+        }
+    }
+}
+
+/// Overall verdict from the component distances of two full ISCC codes, the content is
+/// only compared if both have a Content-ID.
+pub fn verdict(a: &IsccCode, b: &IsccCode) -> Verdict {
+    let content = match (&a.cid, &b.cid) {
+        (Some(cida), Some(cidb)) => distance(cida, cidb).ok(),
+        _ => None,
+    };
+    let data = distance(&a.did, &b.did).unwrap_or(64);
+    if a.iid.digest == b.iid.digest {
+        Verdict::Identical
+    } else if content == Some(0) {
+        Verdict::SameContent
+    } else if matches!(content, Some(d) if d <= SIMILARITY_THRESHOLD)
+        || data <= SIMILARITY_THRESHOLD
+    {
+        Verdict::Similar
+    } else {
+        Verdict::Different
+    }
+}
+
+fn symbols_value(code: &str) -> u128 {
+    code.chars().fold(0, |num, c| {
+        num * 58 + SYMBOLS.find(c).unwrap_or_default() as u128
+    })
+}
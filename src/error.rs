@@ -0,0 +1,93 @@
+//! Error type of the ISCC generation pipeline and the exit codes of `iscc-cli`.
+use std::fmt;
+use std::io;
+
+/// Errors returned by [`generate`](crate::generate) and the helper modules.
+///
+/// Each variant maps to an exit code of `iscc-cli`, see [`Error::exit_code`].
+#[derive(Debug)]
+pub enum Error {
+    /// The media type of a file has no ISCC content path (file, media type).
+    UnsupportedMediaType(String, String),
+    /// The media type could not be guessed from the file extension (file).
+    UnknownExtension(String),
+    /// Reading a file failed (file, cause).
+    Io(String, io::Error),
+    /// Text or image extraction failed (file, cause).
+    Extraction(String, String),
+    /// The Apache Tika server could not be reached (cause).
+    TikaUnavailable(String),
+    /// The options for Apache Tika are invalid, e.g. the URL or CA bundle (cause).
+    TikaConfig(String),
+    /// An ISCC code or component given as input is invalid (cause).
+    InvalidIscc(String),
+    /// A batch run finished, but some files failed (failed, total).
+    BatchFailed(usize, usize),
+}
+
+impl Error {
+    /// Exit code of `iscc-cli` for this error.
+    ///
+    /// | Code | Meaning                               |
+    /// |------|---------------------------------------|
+    /// | 0    | Success                               |
+    /// | 1    | Invalid arguments or other errors     |
+    /// | 2    | Batch finished with failed files      |
+    /// | 3    | I/O error                             |
+    /// | 4    | Unknown file extension                |
+    /// | 5    | Unsupported media type                |
+    /// | 6    | Text or image extraction failed       |
+    /// | 7    | Apache Tika server unavailable        |
+    /// | 8    | Invalid ISCC input                    |
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::BatchFailed(_, _) => 2,
+            Error::Io(_, _) => 3,
+            Error::UnknownExtension(_) => 4,
+            Error::UnsupportedMediaType(_, _) => 5,
+            Error::Extraction(_, _) => 6,
+            Error::TikaUnavailable(_) => 7,
+            Error::TikaConfig(_) => 1,
+            Error::InvalidIscc(_) => 8,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::UnsupportedMediaType(file, mimetype) => {
+                write!(f, "{} -- Mediatype {} not supported", file, mimetype)
+            }
+            Error::UnknownExtension(file) => write!(f, "{} -- Unknown file-extension", file),
+            Error::Io(file, cause) if file.is_empty() => write!(f, "{}", cause),
+            Error::Io(file, cause) => write!(f, "{} -- {}", file, cause),
+            Error::Extraction(file, cause) => {
+                write!(f, "{} -- Extraction failed: {}", file, cause)
+            }
+            Error::TikaUnavailable(cause) => {
+                write!(f, "Apache Tika server unavailable: {}", cause)
+            }
+            Error::TikaConfig(cause) => write!(f, "Invalid Apache Tika option: {}", cause),
+            Error::InvalidIscc(cause) => write!(f, "{}", cause),
+            Error::BatchFailed(failed, total) => {
+                write!(f, "{} of {} files failed", failed, total)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(_, cause) => Some(cause),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(String::new(), error)
+    }
+}
@@ -0,0 +1,148 @@
+use super::{attribute, first_line, non_empty, read_entry};
+use crate::error::Error;
+use crate::source::Source;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::collections::HashMap;
+use zip::ZipArchive;
+
+//same line width as for html files
+const HTML_WIDTH: usize = 72;
+
+//extract text of an EPUB from its spine-ordered XHTML documents,
+//the title is taken from the first dc:title of the package (OPF) or the first non-empty line
+pub fn extract(source: &Source) -> Result<(String, String, String), Error> {
+    let fail = |cause: String| Error::Extraction(source.name().to_string(), cause);
+    let mut archive = ZipArchive::new(source.open()?).map_err(|e| fail(e.to_string()))?;
+    let container = read_entry(&mut archive, "META-INF/container.xml").map_err(fail)?;
+    let opf_path = rootfile(&container)
+        .ok_or_else(|| fail("META-INF/container.xml: No rootfile".to_string()))?;
+    let opf = read_entry(&mut archive, &opf_path).map_err(fail)?;
+    let package = Package::parse(&opf).map_err(|e| fail(format!("{}: {}", opf_path, e)))?;
+
+    // hrefs in the package are relative to the package document
+    let base = opf_path.rfind('/').map_or("", |i| &opf_path[..=i]);
+    let mut contents = String::new();
+    for href in package.documents() {
+        let name = resolve(base, href);
+        let document = archive
+            .by_name(&name)
+            .map_err(|e| fail(format!("{}: {}", name, e)))?;
+        contents.push_str(&html2text::from_read(document, HTML_WIDTH));
+        contents.push('\n');
+    }
+    let title = package.title.unwrap_or_else(|| first_line(&contents));
+    Ok((contents, title, "".to_string()))
+}
+
+//path of the package document from META-INF/container.xml
+fn rootfile(xml: &str) -> Option<String> {
+    let mut reader = Reader::from_str(xml);
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_event(&mut buf).ok()? {
+            Event::Start(e) | Event::Empty(e) if e.local_name() == b"rootfile" => {
+                return attribute(&e, b"full-path");
+            }
+            Event::Eof => return None,
+            _ => {}
+        }
+        buf.clear();
+    }
+}
+
+#[derive(Debug, Default)]
+struct Package {
+    title: Option<String>,
+    //manifest id -> (href, media-type)
+    manifest: HashMap<String, (String, String)>,
+    //manifest ids in reading order
+    spine: Vec<String>,
+}
+
+impl Package {
+    fn parse(xml: &str) -> Result<Package, String> {
+        let mut reader = Reader::from_str(xml);
+        let mut buf = Vec::new();
+        let mut package = Package::default();
+        let mut in_title = false;
+        loop {
+            match reader.read_event(&mut buf) {
+                Ok(Event::Start(e)) if e.local_name() == b"title" => in_title = true,
+                Ok(Event::End(e)) if e.local_name() == b"title" => in_title = false,
+                Ok(Event::Text(e)) if in_title && package.title.is_none() => {
+                    let title = e.unescape_and_decode(&reader).map_err(|e| e.to_string())?;
+                    package.title = non_empty(&title);
+                }
+                Ok(Event::Start(e)) | Ok(Event::Empty(e)) => match e.local_name() {
+                    b"item" => {
+                        let id = attribute(&e, b"id").unwrap_or_default();
+                        let href = attribute(&e, b"href").unwrap_or_default();
+                        let mediatype = attribute(&e, b"media-type").unwrap_or_default();
+                        package.manifest.insert(id, (href, mediatype));
+                    }
+                    b"itemref" => {
+                        if let Some(idref) = attribute(&e, b"idref") {
+                            package.spine.push(idref);
+                        }
+                    }
+                    _ => {}
+                },
+                Ok(Event::Eof) => break,
+                Err(e) => return Err(e.to_string()),
+                _ => {}
+            }
+            buf.clear();
+        }
+        Ok(package)
+    }
+
+    //hrefs of the (X)HTML documents of the spine
+    fn documents(&self) -> impl Iterator<Item = &str> {
+        self.spine
+            .iter()
+            .filter_map(move |id| self.manifest.get(id))
+            .filter(|(_, mediatype)| {
+                mediatype == "application/xhtml+xml" || mediatype == "text/html"
+            })
+            .map(|(href, _)| href.as_str())
+    }
+}
+
+//zip entry name of an href relative to the package directory
+fn resolve(base: &str, href: &str) -> String {
+    let href = href.split('#').next().unwrap_or("");
+    let mut parts: Vec<&str> = base.split('/').filter(|p| !p.is_empty()).collect();
+    for part in href.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            _ => parts.push(part),
+        }
+    }
+    percent_decode(&parts.join("/"))
+}
+
+fn percent_decode(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = path
+            .get(i + 1..i + 3)
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
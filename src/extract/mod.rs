@@ -0,0 +1,68 @@
+extern crate cfb;
+extern crate encoding_rs;
+extern crate quick_xml;
+pub mod epub;
+pub mod language;
+pub mod odf;
+pub mod ole;
+pub mod ooxml;
+pub mod pdf;
+pub mod rtf;
+
+use encoding_rs::{Encoding, WINDOWS_1252};
+use quick_xml::events::BytesStart;
+use std::io::{Read, Seek};
+use zip::ZipArchive;
+
+//first non-empty line of the extracted text, used as title if there is no metadata title
+fn first_line(contents: &str) -> String {
+    contents
+        .lines()
+        .find(|l| l.trim() != "")
+        .unwrap_or("")
+        .to_string()
+}
+
+fn non_empty(title: &str) -> Option<String> {
+    let title = title.trim();
+    if title.is_empty() {
+        None
+    } else {
+        Some(title.to_string())
+    }
+}
+
+//read a text entry of a zip based document format
+fn read_entry<R: Read + Seek>(archive: &mut ZipArchive<R>, name: &str) -> Result<String, String> {
+    let mut entry = archive
+        .by_name(name)
+        .map_err(|e| format!("{}: {}", name, e))?;
+    let mut xml = String::new();
+    entry
+        .read_to_string(&mut xml)
+        .map_err(|e| format!("{}: {}", name, e))?;
+    Ok(xml)
+}
+
+//unescaped value of an xml attribute
+fn attribute(element: &BytesStart, key: &[u8]) -> Option<String> {
+    let attribute = element.attributes().flatten().find(|a| a.key == key)?;
+    let value = attribute.unescaped_value().ok()?;
+    Some(String::from_utf8_lossy(&value).to_string())
+}
+
+//encoding of a windows codepage number, as used by RTF and OLE property sets
+fn codepage_encoding(codepage: u32) -> &'static Encoding {
+    let label = match codepage {
+        932 => "shift_jis".to_string(),
+        936 => "gbk".to_string(),
+        949 => "euc-kr".to_string(),
+        950 => "big5".to_string(),
+        1200 => "utf-16le".to_string(),
+        10000 => "macintosh".to_string(),
+        20866 => "koi8-r".to_string(),
+        65001 => "utf-8".to_string(),
+        codepage => format!("windows-{}", codepage),
+    };
+    Encoding::for_label(label.as_bytes()).unwrap_or(WINDOWS_1252)
+}
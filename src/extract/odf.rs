@@ -0,0 +1,87 @@
+use super::{attribute, first_line, non_empty, read_entry};
+use crate::error::Error;
+use crate::source::Source;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use zip::ZipArchive;
+
+//extract text of an OpenDocument file (odt, ods, odp) from content.xml,
+//the title is taken from dc:title in meta.xml or the first non-empty line
+pub fn extract(source: &Source) -> Result<(String, String, String), Error> {
+    let fail = |cause: String| Error::Extraction(source.name().to_string(), cause);
+    let mut archive = ZipArchive::new(source.open()?).map_err(|e| fail(e.to_string()))?;
+    let content = read_entry(&mut archive, "content.xml").map_err(fail)?;
+    let contents = content_text(&content).map_err(fail)?;
+    let title = read_entry(&mut archive, "meta.xml")
+        .ok()
+        .and_then(|meta| meta_title(&meta))
+        .unwrap_or_else(|| first_line(&contents));
+    Ok((contents, title, "".to_string()))
+}
+
+//spaces of a <text:s text:c="N"/>, the count comes from the file and runs of
+//whitespace are collapsed by text normalization anyway
+const MAX_SPACES: usize = 1000;
+
+//text of office:body, one line per paragraph/heading, annotations are skipped
+fn content_text(xml: &str) -> Result<String, String> {
+    let mut reader = Reader::from_str(xml);
+    let mut buf = Vec::new();
+    let mut text = String::new();
+    let mut body = false;
+    let mut annotation = 0;
+    loop {
+        match reader.read_event(&mut buf) {
+            Ok(Event::Start(e)) => match e.name() {
+                b"office:body" => body = true,
+                b"office:annotation" => annotation += 1,
+                _ => {}
+            },
+            Ok(Event::Empty(e)) if body && annotation == 0 => match e.name() {
+                b"text:s" => {
+                    let count = attribute(&e, b"text:c")
+                        .and_then(|c| c.parse().ok())
+                        .unwrap_or(1);
+                    text.push_str(&" ".repeat(count.min(MAX_SPACES)));
+                }
+                b"text:tab" => text.push('\t'),
+                b"text:line-break" | b"text:p" | b"text:h" => text.push('\n'),
+                _ => {}
+            },
+            Ok(Event::Text(e)) if body && annotation == 0 => {
+                let t = e
+                    .unescape_and_decode(&reader)
+                    .map_err(|e| format!("content.xml: {}", e))?;
+                text.push_str(&t);
+            }
+            Ok(Event::End(e)) => match e.name() {
+                b"office:body" => body = false,
+                b"office:annotation" => annotation -= 1,
+                b"text:p" | b"text:h" if annotation == 0 => text.push('\n'),
+                _ => {}
+            },
+            Ok(Event::Eof) => break,
+            Err(e) => return Err(format!("content.xml: {}", e)),
+            _ => {}
+        }
+        buf.clear();
+    }
+    Ok(text)
+}
+
+//dc:title of meta.xml
+fn meta_title(xml: &str) -> Option<String> {
+    let mut reader = Reader::from_str(xml);
+    let mut buf = Vec::new();
+    let mut in_title = false;
+    loop {
+        match reader.read_event(&mut buf).ok()? {
+            Event::Start(e) if e.name() == b"dc:title" => in_title = true,
+            Event::Text(e) if in_title => return non_empty(&e.unescape_and_decode(&reader).ok()?),
+            Event::End(e) if e.name() == b"dc:title" => return None,
+            Event::Eof => return None,
+            _ => {}
+        }
+        buf.clear();
+    }
+}
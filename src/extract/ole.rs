@@ -0,0 +1,422 @@
+use super::{codepage_encoding, first_line, non_empty};
+use crate::error::Error;
+use crate::source::Source;
+use cfb::CompoundFile;
+use encoding_rs::WINDOWS_1252;
+use std::io::{Read, Seek};
+
+//extract text of an OLE2 compound document (doc, xls, ppt), the title is taken
+//from the SummaryInformation property set or the first non-empty line
+pub fn extract(source: &Source) -> Result<(String, String, String), Error> {
+    let fail = |cause: String| Error::Extraction(source.name().to_string(), cause);
+    let mut ole = CompoundFile::open(source.open()?).map_err(|e| fail(e.to_string()))?;
+    let contents = match document_type(&ole) {
+        Some(OleDocument::Word) => word_text(&mut ole),
+        Some(OleDocument::Excel) => excel_text(&mut ole),
+        Some(OleDocument::PowerPoint) => powerpoint_text(&mut ole),
+        None => Err("No Word, Excel or PowerPoint document stream".to_string()),
+    }
+    .map_err(fail)?;
+    let title = summary_title(&mut ole).unwrap_or_else(|| first_line(&contents));
+    Ok((contents, title, "".to_string()))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OleDocument {
+    Word,
+    Excel,
+    PowerPoint,
+}
+
+impl OleDocument {
+    pub fn mimetype(self) -> &'static str {
+        match self {
+            OleDocument::Word => "application/msword",
+            OleDocument::Excel => "application/vnd.ms-excel",
+            OleDocument::PowerPoint => "application/vnd.ms-powerpoint",
+        }
+    }
+}
+
+//type of document from the streams of a compound file
+pub fn document_type<F: Read + Seek>(ole: &CompoundFile<F>) -> Option<OleDocument> {
+    if ole.is_stream("/WordDocument") {
+        Some(OleDocument::Word)
+    } else if ole.is_stream("/Workbook") || ole.is_stream("/Book") {
+        Some(OleDocument::Excel)
+    } else if ole.is_stream("/PowerPoint Document") {
+        Some(OleDocument::PowerPoint)
+    } else {
+        None
+    }
+}
+
+fn read_stream<F: Read + Seek>(ole: &mut CompoundFile<F>, name: &str) -> Result<Vec<u8>, String> {
+    let mut data = Vec::new();
+    ole.open_stream(name)
+        .and_then(|mut stream| stream.read_to_end(&mut data))
+        .map_err(|e| format!("{}: {}", name.trim_start_matches('/'), e))?;
+    Ok(data)
+}
+
+fn u16_at(data: &[u8], pos: usize) -> Option<u16> {
+    let bytes = data.get(pos..pos + 2)?;
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn u32_at(data: &[u8], pos: usize) -> Option<u32> {
+    let bytes = data.get(pos..pos + 4)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn utf16_at(data: &[u8], pos: usize, count: usize) -> Option<String> {
+    let bytes = data.get(pos..pos + count * 2)?;
+    let units: Vec<u16> = bytes
+        .chunks(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .collect();
+    Some(String::from_utf16_lossy(&units))
+}
+
+//main document text of a Word 97-2003 document from the piece table
+fn word_text<F: Read + Seek>(ole: &mut CompoundFile<F>) -> Result<String, String> {
+    let word = read_stream(ole, "/WordDocument")?;
+    let flags = u16_at(&word, 0x0A).ok_or("WordDocument: Invalid FIB")?;
+    if flags & 0x0100 != 0 {
+        return Err("Encrypted Word document".to_string());
+    }
+    let table_name = if flags & 0x0200 != 0 {
+        "/1Table"
+    } else {
+        "/0Table"
+    };
+    let table = read_stream(ole, table_name)?;
+    piece_text(&word, &table).ok_or_else(|| "WordDocument: Invalid piece table".to_string())
+}
+
+fn piece_text(word: &[u8], table: &[u8]) -> Option<String> {
+    // FibBase (32 bytes), then csw + FibRgW97, cslw + FibRgLw97, cbRgFcLcb + FibRgFcLcb
+    let csw = usize::from(u16_at(word, 32)?);
+    let rglw = 34 + csw * 2;
+    let cslw = usize::from(u16_at(word, rglw)?);
+    let ccp_text = u32_at(word, rglw + 2 + 3 * 4)? as usize;
+    let rgfclcb = rglw + 2 + cslw * 4 + 2;
+    let fc_clx = u32_at(word, rgfclcb + 33 * 8)? as usize;
+    let lcb_clx = u32_at(word, rgfclcb + 33 * 8 + 4)? as usize;
+    let clx = table.get(fc_clx..fc_clx + lcb_clx)?;
+
+    // skip the property modifiers (Prc) before the piece table (Pcdt)
+    let mut pos = 0;
+    while clx.get(pos) == Some(&1) {
+        pos += 3 + usize::from(u16_at(clx, pos + 1)?);
+    }
+    if clx.get(pos) != Some(&2) {
+        return None;
+    }
+    let lcb = u32_at(clx, pos + 1)? as usize;
+    let plc = clx.get(pos + 5..pos + 5 + lcb)?;
+    let pieces = lcb.checked_sub(4)? / 12;
+
+    let mut text = String::new();
+    for i in 0..pieces {
+        let cp_start = u32_at(plc, i * 4)? as usize;
+        let cp_end = (u32_at(plc, i * 4 + 4)? as usize).min(ccp_text);
+        if cp_start >= cp_end {
+            continue;
+        }
+        let count = cp_end - cp_start;
+        let fc = u32_at(plc, (pieces + 1) * 4 + i * 8 + 2)?;
+        if fc & 0x4000_0000 != 0 {
+            // compressed piece, one byte per character
+            let offset = (fc & 0x3FFF_FFFF) as usize / 2;
+            let (piece, _, _) = WINDOWS_1252.decode(word.get(offset..offset + count)?);
+            text.push_str(&piece);
+        } else {
+            text.push_str(&utf16_at(word, fc as usize, count)?);
+        }
+    }
+    Some(clean_word_text(&text))
+}
+
+//map Word control characters, field instructions are dropped and field results kept
+fn clean_word_text(text: &str) -> String {
+    let mut clean = String::with_capacity(text.len());
+    //true for each open field while in its instruction part
+    let mut fields: Vec<bool> = Vec::new();
+    for c in text.chars() {
+        match c {
+            '\u{13}' => fields.push(true),
+            '\u{14}' => {
+                if let Some(instruction) = fields.last_mut() {
+                    *instruction = false;
+                }
+            }
+            '\u{15}' => {
+                fields.pop();
+            }
+            _ if fields.iter().any(|&instruction| instruction) => {}
+            '\r' | '\u{0b}' | '\u{0c}' => clean.push('\n'),
+            '\u{07}' => clean.push('\t'),
+            '\u{1e}' => clean.push('-'),
+            '\t' => clean.push('\t'),
+            c if c < ' ' => {}
+            c => clean.push(c),
+        }
+    }
+    clean
+}
+
+const XLS_BOF: u16 = 0x0809;
+const XLS_SST: u16 = 0x00FC;
+const XLS_CONTINUE: u16 = 0x003C;
+const XLS_LABELSST: u16 = 0x00FD;
+const XLS_LABEL: u16 = 0x0204;
+
+//cell strings of a BIFF workbook, one line per row and tabs between cells
+fn excel_text<F: Read + Seek>(ole: &mut CompoundFile<F>) -> Result<String, String> {
+    let stream = read_stream(ole, "/Workbook").or_else(|_| read_stream(ole, "/Book"))?;
+    let mut records = Vec::new();
+    let mut pos = 0;
+    while let (Some(rtype), Some(len)) = (u16_at(&stream, pos), u16_at(&stream, pos + 2)) {
+        let end = (pos + 4 + usize::from(len)).min(stream.len());
+        records.push((rtype, &stream[pos + 4..end]));
+        pos = end;
+    }
+
+    let mut sst = Vec::new();
+    let mut text = String::new();
+    let mut biff8 = true;
+    let mut row = None;
+    for (i, &(rtype, data)) in records.iter().enumerate() {
+        let cell = match rtype {
+            XLS_BOF => {
+                biff8 = u16_at(data, 0) == Some(0x0600);
+                if row.take().is_some() {
+                    text.push('\n');
+                }
+                continue;
+            }
+            XLS_SST => {
+                let mut segments = vec![data];
+                segments.extend(
+                    records[i + 1..]
+                        .iter()
+                        .take_while(|(rtype, _)| *rtype == XLS_CONTINUE)
+                        .map(|(_, data)| *data),
+                );
+                sst = shared_strings(segments);
+                continue;
+            }
+            XLS_LABELSST => u32_at(data, 6)
+                .and_then(|isst| sst.get(isst as usize))
+                .map(|s| s.to_string()),
+            XLS_LABEL if biff8 => {
+                let mut segments = Segments::new(vec![data.get(6..).unwrap_or(&[])]);
+                segments.string(false)
+            }
+            XLS_LABEL => {
+                let cch = usize::from(u16_at(data, 6).unwrap_or(0));
+                data.get(8..8 + cch)
+                    .map(|bytes| WINDOWS_1252.decode(bytes).0.to_string())
+            }
+            _ => continue,
+        };
+        let cell_row = u16_at(data, 0);
+        match row {
+            Some(r) if Some(r) == cell_row => text.push('\t'),
+            Some(_) => text.push('\n'),
+            None => {}
+        }
+        row = cell_row;
+        text.push_str(&cell.unwrap_or_default());
+    }
+    Ok(text)
+}
+
+//strings of the shared string table, which may span CONTINUE records
+fn shared_strings(segments: Vec<&[u8]>) -> Vec<String> {
+    let mut segments = Segments::new(segments);
+    let count = segments.skip(4).and_then(|_| segments.u32()).unwrap_or(0);
+    let mut strings = Vec::new();
+    for _ in 0..count {
+        match segments.string(true) {
+            Some(s) => strings.push(s),
+            None => break,
+        }
+    }
+    strings
+}
+
+//reader over the data of a record and its CONTINUE records
+struct Segments<'a> {
+    segments: Vec<&'a [u8]>,
+    index: usize,
+    pos: usize,
+}
+
+impl<'a> Segments<'a> {
+    fn new(segments: Vec<&'a [u8]>) -> Segments<'a> {
+        Segments {
+            segments,
+            index: 0,
+            pos: 0,
+        }
+    }
+
+    fn at_end_of_segment(&self) -> bool {
+        !matches!(self.segments.get(self.index), Some(s) if self.pos < s.len())
+    }
+
+    fn next_segment(&mut self) -> Option<()> {
+        self.index += 1;
+        self.pos = 0;
+        if self.index < self.segments.len() {
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        while self.at_end_of_segment() {
+            self.next_segment()?;
+        }
+        let byte = self.segments[self.index][self.pos];
+        self.pos += 1;
+        Some(byte)
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        Some(u16::from_le_bytes([self.u8()?, self.u8()?]))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes([
+            self.u8()?,
+            self.u8()?,
+            self.u8()?,
+            self.u8()?,
+        ]))
+    }
+
+    fn skip(&mut self, count: usize) -> Option<()> {
+        for _ in 0..count {
+            self.u8()?;
+        }
+        Some(())
+    }
+
+    //XLUnicodeRichExtendedString (sst) or XLUnicodeString (label)
+    fn string(&mut self, rich: bool) -> Option<String> {
+        let cch = self.u16()?;
+        let flags = self.u8()?;
+        let mut high_byte = flags & 0x01 != 0;
+        let runs = if rich && flags & 0x08 != 0 {
+            self.u16()?
+        } else {
+            0
+        };
+        let ext = if rich && flags & 0x04 != 0 {
+            self.u32()?
+        } else {
+            0
+        };
+        let mut units = Vec::with_capacity(usize::from(cch));
+        for _ in 0..cch {
+            // characters continued in the next record start with a new flags byte
+            if self.at_end_of_segment() {
+                self.next_segment()?;
+                high_byte = self.u8()? & 0x01 != 0;
+            }
+            let unit = if high_byte {
+                self.u16()?
+            } else {
+                u16::from(self.u8()?)
+            };
+            units.push(unit);
+        }
+        self.skip(usize::from(runs) * 4 + ext as usize)?;
+        Some(String::from_utf16_lossy(&units))
+    }
+}
+
+const PPT_MAIN_MASTER: u16 = 0x03F8;
+const PPT_TEXT_CHARS: u16 = 0x0FA0;
+const PPT_TEXT_BYTES: u16 = 0x0FA8;
+//containers nested deeper are skipped, real presentations stay far below
+const PPT_MAX_DEPTH: usize = 32;
+
+//text atoms of a PowerPoint 97-2003 presentation, master slides are skipped
+fn powerpoint_text<F: Read + Seek>(ole: &mut CompoundFile<F>) -> Result<String, String> {
+    let stream = read_stream(ole, "/PowerPoint Document")?;
+    let mut text = String::new();
+    text_atoms(&stream, &mut text, 0);
+    Ok(text)
+}
+
+fn text_atoms(data: &[u8], text: &mut String, depth: usize) {
+    let mut pos = 0;
+    while let (Some(version), Some(rtype), Some(len)) = (
+        u16_at(data, pos),
+        u16_at(data, pos + 2),
+        u32_at(data, pos + 4),
+    ) {
+        let end = (pos + 8).saturating_add(len as usize).min(data.len());
+        let body = &data[pos + 8..end];
+        let atom = match rtype {
+            // containers have a record version of 0xF
+            _ if version & 0x0F == 0x0F => {
+                if rtype != PPT_MAIN_MASTER && depth < PPT_MAX_DEPTH {
+                    text_atoms(body, text, depth + 1);
+                }
+                None
+            }
+            PPT_TEXT_CHARS => utf16_at(body, 0, body.len() / 2),
+            PPT_TEXT_BYTES => Some(WINDOWS_1252.decode(body).0.to_string()),
+            _ => None,
+        };
+        if let Some(atom) = atom {
+            text.push_str(&atom.replace(['\r', '\u{0b}'], "\n"));
+            text.push('\n');
+        }
+        pos = end;
+    }
+}
+
+const PID_CODEPAGE: u32 = 1;
+const PID_TITLE: u32 = 2;
+const VT_LPSTR: u32 = 0x1E;
+const VT_LPWSTR: u32 = 0x1F;
+
+//title of the SummaryInformation property set
+fn summary_title<F: Read + Seek>(ole: &mut CompoundFile<F>) -> Option<String> {
+    let data = read_stream(ole, "/\u{5}SummaryInformation").ok()?;
+    let section = u32_at(&data, 44)? as usize;
+    let count = u32_at(&data, section + 4)? as usize;
+    let mut codepage = 1252;
+    let mut title = None;
+    for i in 0..count {
+        let id = u32_at(&data, section + 8 + i * 8)?;
+        let offset = section + u32_at(&data, section + 12 + i * 8)? as usize;
+        match id {
+            PID_CODEPAGE => codepage = u16_at(&data, offset + 4)?,
+            PID_TITLE => title = Some(offset),
+            _ => {}
+        }
+    }
+    let offset = title?;
+    let size = u32_at(&data, offset + 4)? as usize;
+    let title = match u32_at(&data, offset)? {
+        VT_LPSTR if codepage == 1200 => utf16_at(&data, offset + 8, size / 2)?,
+        VT_LPSTR => {
+            let bytes = data.get(offset + 8..offset + 8 + size)?;
+            codepage_encoding(u32::from(codepage))
+                .decode(bytes)
+                .0
+                .to_string()
+        }
+        VT_LPWSTR => utf16_at(&data, offset + 8, size)?,
+        _ => return None,
+    };
+    non_empty(title.trim_end_matches('\0'))
+}
@@ -0,0 +1,92 @@
+use super::first_line;
+use crate::error::Error;
+use crate::source::Source;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::io::Read;
+use zip::ZipArchive;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OoxmlDocument {
+    Word,
+    Excel,
+    PowerPoint,
+}
+
+//extract text of an Office Open XML document (docx, xlsx, pptx),
+//the title is the first non-empty line
+pub fn extract(
+    source: &Source,
+    document: OoxmlDocument,
+) -> Result<(String, String, String), Error> {
+    let fail = |cause: String| Error::Extraction(source.name().to_string(), cause);
+    let mut archive = ZipArchive::new(source.open()?).map_err(|e| fail(e.to_string()))?;
+
+    // text of the xml parts holding it, in archive order
+    let mut contents = String::new();
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i).map_err(|e| fail(e.to_string()))?;
+        let name = entry.name().to_string();
+        let is_text = match document {
+            OoxmlDocument::Word => name == "word/document.xml",
+            OoxmlDocument::Excel => {
+                name == "xl/sharedStrings.xml"
+                    || name.starts_with("xl/charts/")
+                    || (name.starts_with("xl/worksheets") && name.ends_with(".xml"))
+            }
+            OoxmlDocument::PowerPoint => name.starts_with("ppt/slides"),
+        };
+        if is_text {
+            let mut xml = String::new();
+            entry
+                .read_to_string(&mut xml)
+                .map_err(|e| fail(format!("{}: {}", name, e)))?;
+            let part = text(&xml, document).map_err(|e| fail(format!("{}: {}", name, e)))?;
+            contents.push_str(&part);
+        }
+    }
+    let title = first_line(&contents);
+    Ok((contents, title, "".to_string()))
+}
+
+//text runs of an xml part, a paragraph starts a new line (two for Word)
+//and so does every cell and text run of a spreadsheet
+fn text(xml: &str, document: OoxmlDocument) -> Result<String, String> {
+    let mut reader = Reader::from_str(xml);
+    let mut buf = Vec::new();
+    let mut text = String::new();
+    let mut to_read = false;
+    loop {
+        match reader.read_event(&mut buf) {
+            Ok(Event::Start(e)) => match (document, e.name()) {
+                (OoxmlDocument::Word, b"w:p") => {
+                    text.push_str("\n\n");
+                    to_read = true;
+                }
+                (OoxmlDocument::Word, b"w:t") => to_read = true,
+                (OoxmlDocument::Excel, b"t") | (OoxmlDocument::Excel, b"a:t") => {
+                    text.push('\n');
+                    to_read = true;
+                }
+                (OoxmlDocument::PowerPoint, b"a:p") => {
+                    text.push('\n');
+                    to_read = true;
+                }
+                (OoxmlDocument::PowerPoint, b"a:t") => to_read = true,
+                _ => {}
+            },
+            Ok(Event::Text(e)) if to_read => {
+                let t = e
+                    .unescape_and_decode(&reader)
+                    .map_err(|e| format!("Position {}: {}", reader.buffer_position(), e))?;
+                text.push_str(&t);
+                to_read = false;
+            }
+            Ok(Event::Eof) => break,
+            Err(e) => return Err(format!("Position {}: {}", reader.buffer_position(), e)),
+            _ => {}
+        }
+        buf.clear();
+    }
+    Ok(text)
+}
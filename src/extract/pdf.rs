@@ -0,0 +1,58 @@
+extern crate pdf_extract;
+use super::{first_line, non_empty};
+use crate::error::Error;
+use crate::source::Source;
+use pdf_extract::{decode_text_string, output_doc, Document, PlainTextOutput};
+
+//extract text and title of a pdf, the title is taken from the document info,
+//the XMP metadata or the first non-empty line (in that order)
+pub fn extract(source: &Source) -> Result<(String, String, String), Error> {
+    let fail = |cause: String| Error::Extraction(source.name().to_string(), cause);
+    let mut doc = Document::load_mem(&source.read()?).map_err(|e| fail(e.to_string()))?;
+    if doc.is_encrypted() {
+        // documents with an owner password only can be read without a password
+        doc.decrypt("").map_err(|e| fail(e.to_string()))?;
+    }
+    let mut contents = String::new();
+    output_doc(&doc, &mut PlainTextOutput::new(&mut contents)).map_err(|e| fail(e.to_string()))?;
+
+    let title = info_title(&doc)
+        .or_else(|| xmp_title(&doc))
+        .unwrap_or_else(|| first_line(&contents));
+    Ok((contents, title, "".to_string()))
+}
+
+//title from the document information dictionary
+fn info_title(doc: &Document) -> Option<String> {
+    let (_, info) = doc.dereference(doc.trailer.get(b"Info").ok()?).ok()?;
+    let (_, title) = doc
+        .dereference(info.as_dict().ok()?.get(b"Title").ok()?)
+        .ok()?;
+    non_empty(&decode_text_string(title).ok()?)
+}
+
+//dc:title from the XMP metadata stream of the document catalog
+fn xmp_title(doc: &Document) -> Option<String> {
+    let (_, metadata) = doc
+        .dereference(doc.catalog().ok()?.get(b"Metadata").ok()?)
+        .ok()?;
+    let stream = metadata.as_stream().ok()?;
+    let xmp = stream
+        .decompressed_content()
+        .unwrap_or_else(|_| stream.content.clone());
+    let xmp = String::from_utf8_lossy(&xmp);
+    let title = &xmp[xmp.find("<dc:title")?..];
+    let title = &title[..title.find("</dc:title>")?];
+    let item = &title[title.find("<rdf:li")?..];
+    let item = &item[item.find('>')? + 1..item.find("</rdf:li>")?];
+    non_empty(&unescape_xml(item))
+}
+
+//replace the predefined XML entities
+fn unescape_xml(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
@@ -0,0 +1,268 @@
+use super::{codepage_encoding, first_line, non_empty};
+use crate::error::Error;
+use crate::source::Source;
+use encoding_rs::{Encoding, WINDOWS_1252};
+
+//extract text of an RTF document, the title is taken from \title in the \info group
+//or the first non-empty line
+pub fn extract(source: &Source) -> Result<(String, String, String), Error> {
+    let data = source.read()?;
+    if !data.starts_with(b"{\\rtf") {
+        return Err(Error::Extraction(
+            source.name().to_string(),
+            "Not an RTF document".to_string(),
+        ));
+    }
+    let mut parser = Parser::new(&data);
+    parser.parse();
+    let title = non_empty(&parser.title).unwrap_or_else(|| first_line(&parser.text));
+    Ok((parser.text, title, "".to_string()))
+}
+
+//destinations whose text is not part of the document content
+const IGNORED: &[&str] = &[
+    "author",
+    "colortbl",
+    "comment",
+    "datastore",
+    "doccomm",
+    "fldinst",
+    "filetbl",
+    "fonttbl",
+    "footer",
+    "footerf",
+    "footerl",
+    "footerr",
+    "header",
+    "headerf",
+    "headerl",
+    "headerr",
+    "latentstyles",
+    "listoverridetable",
+    "listtable",
+    "object",
+    "operator",
+    "pict",
+    "private",
+    "revtbl",
+    "rsidtbl",
+    "stylesheet",
+    "themedata",
+    "colorschememapping",
+    "xmlnstbl",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Destination {
+    Text,
+    //document information, only \title is extracted
+    Info,
+    Title,
+    Ignore,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Group {
+    destination: Destination,
+    //number of ANSI characters following a \u control word
+    uc: usize,
+}
+
+struct Parser<'a> {
+    data: &'a [u8],
+    pos: usize,
+    encoding: &'static Encoding,
+    group: Group,
+    stack: Vec<Group>,
+    //codepage bytes not decoded yet
+    pending: Vec<u8>,
+    //ANSI characters left to skip after \u
+    skip: usize,
+    //high surrogate of a \u waiting for the \u with the low surrogate
+    surrogate: Option<u16>,
+    text: String,
+    title: String,
+}
+
+impl<'a> Parser<'a> {
+    fn new(data: &'a [u8]) -> Parser<'a> {
+        Parser {
+            data,
+            pos: 0,
+            encoding: WINDOWS_1252,
+            group: Group {
+                destination: Destination::Text,
+                uc: 1,
+            },
+            stack: Vec::new(),
+            pending: Vec::new(),
+            skip: 0,
+            surrogate: None,
+            text: String::new(),
+            title: String::new(),
+        }
+    }
+
+    fn parse(&mut self) {
+        while let Some(&byte) = self.data.get(self.pos) {
+            self.pos += 1;
+            match byte {
+                b'{' => {
+                    self.flush();
+                    self.stack.push(self.group);
+                    self.skip = 0;
+                    if self.data[self.pos..].starts_with(b"\\*") {
+                        self.pos += 2;
+                        self.group.destination = Destination::Ignore;
+                    }
+                }
+                b'}' => {
+                    self.flush();
+                    self.skip = 0;
+                    if let Some(group) = self.stack.pop() {
+                        self.group = group;
+                    }
+                }
+                b'\\' => self.control(),
+                b'\r' | b'\n' => {}
+                byte => self.push_byte(byte),
+            }
+        }
+        self.flush();
+    }
+
+    fn control(&mut self) {
+        let start = self.pos;
+        while matches!(self.data.get(self.pos), Some(b) if b.is_ascii_alphabetic()) {
+            self.pos += 1;
+        }
+        if self.pos == start {
+            return self.symbol();
+        }
+        let word = String::from_utf8_lossy(&self.data[start..self.pos]).to_string();
+        let param_start = self.pos;
+        if self.data.get(self.pos) == Some(&b'-') {
+            self.pos += 1;
+        }
+        while matches!(self.data.get(self.pos), Some(b) if b.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        let param: Option<i32> = String::from_utf8_lossy(&self.data[param_start..self.pos])
+            .parse()
+            .ok();
+        // a space delimiting the control word is part of it
+        if self.data.get(self.pos) == Some(&b' ') {
+            self.pos += 1;
+        }
+        // \u keeps a high surrogate for the next \u
+        if word != "u" {
+            self.flush();
+        }
+        match word.as_str() {
+            "par" | "line" | "sect" | "page" | "row" => self.push_char('\n'),
+            "tab" | "cell" => self.push_char('\t'),
+            "emdash" => self.push_char('\u{2014}'),
+            "endash" => self.push_char('\u{2013}'),
+            "emspace" | "enspace" | "qmspace" => self.push_char(' '),
+            "lquote" => self.push_char('\u{2018}'),
+            "rquote" => self.push_char('\u{2019}'),
+            "ldblquote" => self.push_char('\u{201c}'),
+            "rdblquote" => self.push_char('\u{201d}'),
+            "bullet" => self.push_char('\u{2022}'),
+            "u" => {
+                // \uN is a signed 16 bit value
+                self.unicode(param.unwrap_or(0) as i16 as u16);
+                self.skip = self.group.uc;
+            }
+            "uc" => self.group.uc = param.unwrap_or(1).max(0) as usize,
+            "ansicpg" => self.encoding = codepage_encoding(param.unwrap_or(1252) as u32),
+            "bin" => self.pos += param.unwrap_or(0).max(0) as usize,
+            "info" => self.group.destination = Destination::Info,
+            "title" if self.group.destination == Destination::Info => {
+                self.group.destination = Destination::Title
+            }
+            word if IGNORED.contains(&word) => self.group.destination = Destination::Ignore,
+            _ => {}
+        }
+    }
+
+    //control symbols: \'hh, escaped characters and special characters
+    fn symbol(&mut self) {
+        let symbol = match self.data.get(self.pos) {
+            Some(&symbol) => symbol,
+            None => return,
+        };
+        self.pos += 1;
+        match symbol {
+            b'\'' => {
+                let hex = self.data.get(self.pos..self.pos + 2).unwrap_or(b"");
+                let byte = std::str::from_utf8(hex)
+                    .ok()
+                    .and_then(|h| u8::from_str_radix(h, 16).ok());
+                self.pos += hex.len();
+                if let Some(byte) = byte {
+                    self.push_byte(byte);
+                }
+            }
+            b'\\' | b'{' | b'}' => self.push_byte(symbol),
+            b'~' => self.push_char('\u{a0}'),
+            b'_' => self.push_char('-'),
+            b'\r' | b'\n' => self.push_char('\n'),
+            b'*' => self.group.destination = Destination::Ignore,
+            _ => {}
+        }
+    }
+
+    fn push_byte(&mut self, byte: u8) {
+        if self.skip > 0 {
+            self.skip -= 1;
+        } else if matches!(
+            self.group.destination,
+            Destination::Text | Destination::Title
+        ) {
+            self.pending.push(byte);
+        }
+    }
+
+    //UTF-16 code unit of \u, characters outside the BMP are a pair of surrogates
+    fn unicode(&mut self, unit: u16) {
+        match (self.surrogate, unit) {
+            (Some(high), 0xDC00..=0xDFFF) if self.pending.is_empty() => {
+                self.surrogate = None;
+                let c = 0x10000 + (u32::from(high - 0xD800) << 10) + u32::from(unit - 0xDC00);
+                self.push_char(std::char::from_u32(c).unwrap_or('\u{fffd}'));
+            }
+            (_, 0xD800..=0xDBFF) => {
+                self.flush();
+                self.surrogate = Some(unit);
+            }
+            (_, unit) => self.push_char(std::char::from_u32(u32::from(unit)).unwrap_or('\u{fffd}')),
+        }
+    }
+
+    fn push_char(&mut self, c: char) {
+        self.flush();
+        self.write(c.encode_utf8(&mut [0; 4]));
+    }
+
+    fn flush(&mut self) {
+        // a high surrogate not followed by a low one
+        if self.surrogate.take().is_some() {
+            self.write("\u{fffd}");
+        }
+        if self.pending.is_empty() {
+            return;
+        }
+        let pending = std::mem::take(&mut self.pending);
+        let (decoded, _, _) = self.encoding.decode(&pending);
+        self.write(&decoded);
+    }
+
+    fn write(&mut self, s: &str) {
+        match self.group.destination {
+            Destination::Text => self.text.push_str(s),
+            Destination::Title => self.title.push_str(s),
+            Destination::Info | Destination::Ignore => {}
+        }
+    }
+}
@@ -0,0 +1,474 @@
+//! ISCC code generation for files, as used by the `iscc-cli` binary.
+//!
+//! [`generate`] runs the full pipeline for a single file ([`generate_bytes`] for data
+//! in memory): media type
+//! detection, text/title extraction (standalone or via Apache Tika) and the
+//! creation of all four ISCC components with the
+//! [iscc-rs](https://github.com/iscc/iscc-rs) library.
+//!
+//! ```no_run
+//! let options = iscc_cli::Options::default();
+//! let iscc = iscc_cli::generate("tests/test_data/text/demo.docx", &options).unwrap();
+//! println!("ISCC:{}", iscc.code());
+//! ```
+extern crate html2text;
+extern crate image;
+extern crate mime_guess;
+extern crate serde;
+extern crate zip;
+
+mod cid;
+pub mod code;
+mod error;
+mod extract;
+mod media;
+mod source;
+pub mod tika;
+
+pub use error::Error;
+pub use tika::{MetadataKeys, TikaConfig, TikaOptions};
+
+use iscc::{content_id_text, meta_id};
+
+use serde::Serialize;
+use std::collections::HashMap;
+
+use cid::audio::content_id_audio;
+use cid::image::content_id_image;
+use cid::video::content_id_video;
+use media::{detect_mimetype, get_gmt_from_mimetype, get_gmt_from_tika, GeneralMediaType};
+use source::{data_id, instance_id, Source};
+use tika::request::TikaDocument;
+
+/// The ISCC components and metadata generated for a file.
+#[derive(Debug, Default, Serialize)]
+pub struct Iscc {
+    /// Meta-ID
+    pub mid: String,
+    /// Content-ID, empty for containers and data files
+    pub cid: String,
+    /// Data-ID
+    pub did: String,
+    /// Instance-ID
+    pub iid: String,
+    /// General media type (`text`, `image`, `audio`, `video`, `container`, `data`)
+    pub gmt: String,
+    /// Detected media type, e.g. `image/png`
+    pub mediatype: String,
+    /// Normalized title used for the Meta-ID
+    pub title: String,
+    /// Normalized extra text used for the Meta-ID
+    pub extra: String,
+    /// Detected language of the text (ISO 639-1 code), empty if unknown or not text
+    pub language: String,
+    /// Hex encoded tophash of the Instance-ID
+    pub tophash: String,
+    /// Backend that detected the media type and extracted the text (`standalone` or `tika`)
+    pub backend: String,
+    /// Path inside the container file for an embedded document, e.g. `/attachment.pdf`
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub embedded_path: String,
+    /// ISCCs of the documents embedded in the file (with [`Options::embedded`]), not
+    /// serialized (`iscc-cli` writes them as `children` of its JSON result)
+    #[serde(skip)]
+    pub children: Vec<Iscc>,
+    /// Warnings from media type detection, e.g. if content and file extension disagree
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    /// Why no ISCC could be generated for an embedded document, its components are
+    /// empty then
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub error: String,
+}
+
+impl Iscc {
+    /// Join ISCC Components to fully qualified ISCC Code (without `ISCC:` prefix).
+    ///
+    /// The Content-ID is left out if there is none.
+    pub fn code(&self) -> String {
+        [
+            self.mid.as_str(),
+            self.cid.as_str(),
+            self.did.as_str(),
+            self.iid.as_str(),
+        ]
+        .iter()
+        .filter(|component| !component.is_empty())
+        .copied()
+        .collect::<Vec<_>>()
+        .join("-")
+    }
+}
+
+/// Options for [`generate`].
+#[derive(Debug, Default)]
+pub struct Options {
+    /// Title for Meta-ID creation.
+    pub title: String,
+    /// Extra text for Meta-ID creation.
+    pub extra: String,
+    /// Guess title and extra from the file content instead of using `title` and `extra`.
+    pub guess: bool,
+    /// Set the partial content flag of the Content-ID.
+    pub partial: bool,
+    /// Use Apache Tika for media-type detection and text-extraction.
+    pub tika: Option<TikaConfig>,
+    /// Combine Apache Tika (if `tika` is set) and the standalone extractors.
+    pub fallback: Fallback,
+    /// ffmpeg `signature` filter output (XML or binary) with the frame signatures of a video.
+    pub frame_sig: Option<String>,
+    /// Add the detected language to the extra text of the Meta-ID.
+    pub language_extra: bool,
+    /// Apache Tika metadata keys for title and extra (with `guess`).
+    pub metadata_keys: MetadataKeys,
+    /// Generate ISCCs for the documents embedded in a file (attachments, archive contents)
+    /// parsed by Apache Tika.
+    ///
+    /// Text and metadata come from the `/rmeta` answer for the file, the data of the
+    /// documents from Tika's `/unpack` endpoint. Title and extra always come from their
+    /// metadata. Without this option their text is part of the text of the file.
+    pub embedded: bool,
+}
+
+/// Fallback between Apache Tika and the standalone extractors, see [`Options`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Fallback {
+    /// Only Apache Tika if `tika` is set, otherwise only the standalone extractors.
+    #[default]
+    None,
+    /// Standalone extractors first, Apache Tika for files they fail on.
+    Tika,
+    /// Apache Tika first, standalone extractors for files Tika fails on.
+    Standalone,
+}
+
+/// Generate the ISCC for the file at `path`.
+///
+/// With a [`Fallback`] a file the first backend fails on (unsupported media type,
+/// failed extraction, Tika unavailable) is processed with the other one, the error
+/// of the other one is returned if both fail. [`Iscc::backend`] names the backend used.
+pub fn generate(path: &str, options: &Options) -> Result<Iscc, Error> {
+    generate_source(&Source::File(path), None, options)
+}
+
+/// Generate the ISCC for `data` in memory, e.g. read from stdin.
+///
+/// `mimetype` takes the place of the file extension in media type detection, `name`
+/// is used in errors and warnings. Otherwise the same as [`generate`].
+pub fn generate_bytes(
+    name: &str,
+    data: &[u8],
+    mimetype: &str,
+    options: &Options,
+) -> Result<Iscc, Error> {
+    generate_source(&Source::Memory(name, data), Some(mimetype), options)
+}
+
+fn generate_source(
+    source: &Source,
+    declared: Option<&str>,
+    options: &Options,
+) -> Result<Iscc, Error> {
+    let tika = options.tika.as_ref();
+    let (first, second) = match (options.fallback, tika) {
+        (Fallback::Tika, Some(_)) => (None, Some(tika)),
+        (Fallback::Standalone, Some(_)) => (tika, Some(None)),
+        _ => (tika, None),
+    };
+    match (generate_with(source, declared, options, first), second) {
+        (Err(error), Some(second)) if is_fallback_error(&error) => {
+            generate_with(source, declared, options, second)
+        }
+        (result, _) => result,
+    }
+}
+
+//errors the other backend may not run into, i/o errors it would
+fn is_fallback_error(error: &Error) -> bool {
+    matches!(
+        error,
+        Error::UnsupportedMediaType(_, _)
+            | Error::UnknownExtension(_)
+            | Error::Extraction(_, _)
+            | Error::TikaUnavailable(_)
+    )
+}
+
+//generate the ISCC with apache tika or, if `tika` is None, the standalone extractors
+fn generate_with(
+    source: &Source,
+    declared: Option<&str>,
+    options: &Options,
+    tika: Option<&TikaConfig>,
+) -> Result<Iscc, Error> {
+    let path = source.name();
+    let mut warnings = Vec::new();
+    // in Tika mode the file is parsed once, for detection and extraction
+    let document = match tika {
+        Some(tikaconfig) => {
+            let mut document = tika::request::rmeta(tikaconfig, source)?;
+            // without ISCCs of their own embedded documents are content of the file
+            if !options.embedded {
+                document.text = document.text_with_embedded();
+            }
+            Some(document)
+        }
+        None => None,
+    };
+    let (mediatype, mimetype) = match &document {
+        Some(document) => (
+            get_gmt_from_tika(&document.content_type),
+            document.content_type.to_string(),
+        ),
+        None => {
+            let (mimetype, mismatch) = detect_mimetype(source, declared)?;
+            warnings.extend(mismatch);
+            (get_gmt_from_mimetype(path, &mimetype)?, mimetype)
+        }
+    };
+
+    let mut extract = match &document {
+        Some(document) if mediatype.is_tika_extract() => {
+            mediatype.extract_tika(document, &options.metadata_keys)
+        }
+        _ => mediatype.extract(source)?,
+    };
+    if !options.guess {
+        extract.1 = options.title.to_string();
+        extract.2 = options.extra.to_string();
+    }
+    let language = match (&mediatype, tika) {
+        (GeneralMediaType::Text(_), _) if extract.0.trim().is_empty() => None,
+        (GeneralMediaType::Text(_), Some(tikaconfig)) => {
+            tika_language(tikaconfig, path, &extract.0, &mut warnings)
+        }
+        (GeneralMediaType::Text(_), None) => extract::language::detect(&extract.0),
+        _ => None,
+    }
+    .unwrap_or_default();
+    if options.language_extra {
+        extract.2 = with_language(&extract.2, &language);
+    }
+    let (extracted_content, extracted_title, extracted_extra) = extract;
+    let (mid, metatitle, metaextra) = meta_id(&extracted_title, &extracted_extra);
+    let did = data_id(source)?;
+    let (iid, tophash) = instance_id(source)?;
+    let cid = content_id(
+        source,
+        &mediatype,
+        &mimetype,
+        &extracted_content,
+        options.frame_sig.as_deref(),
+        options.partial,
+        tika.is_some(),
+    )?;
+    let iscc = Iscc {
+        mid,
+        cid,
+        did,
+        iid,
+        gmt: mediatype.get_gmt_string(),
+        mediatype: mimetype,
+        title: metatitle,
+        extra: metaextra,
+        language,
+        tophash,
+        backend: if tika.is_some() { "tika" } else { "standalone" }.to_string(),
+        embedded_path: String::new(),
+        children: vec![],
+        warnings,
+        error: String::new(),
+    };
+    let children = match (&document, tika) {
+        (Some(document), Some(tikaconfig)) if options.embedded => {
+            let mut unpacker = Unpacker::new(source, tikaconfig, &document.embedded);
+            document
+                .embedded
+                .iter()
+                .map(|embedded| {
+                    // a failing embedded document doesn't fail the file
+                    generate_embedded(path, embedded, options, tikaconfig, &mut unpacker)
+                        .unwrap_or_else(|error| Iscc {
+                            gmt: get_gmt_from_tika(&embedded.content_type).get_gmt_string(),
+                            mediatype: embedded.content_type.to_string(),
+                            backend: "tika".to_string(),
+                            embedded_path: embedded.embedded_path.to_string(),
+                            error: error.to_string(),
+                            ..Iscc::default()
+                        })
+                })
+                .collect()
+        }
+        _ => vec![],
+    };
+    Ok(Iscc { children, ..iscc })
+}
+
+//Content-ID for the general media type, none for containers and data files. With `tika`
+//any image, audio or video type may come up, those that can't be decoded are data files.
+fn content_id(
+    source: &Source,
+    mediatype: &GeneralMediaType,
+    mimetype: &str,
+    text: &str,
+    frame_sig: Option<&str>,
+    partial: bool,
+    tika: bool,
+) -> Result<String, Error> {
+    let cid = match mediatype {
+        GeneralMediaType::Text(_ft) => Ok(content_id_text(text, partial)),
+        GeneralMediaType::Image(_ft) => content_id_image(source, partial),
+        GeneralMediaType::Audio(_ft) => content_id_audio(source, partial),
+        GeneralMediaType::Video(_ft) => content_id_video(source, mimetype, frame_sig, partial),
+        GeneralMediaType::Container(_ft) | GeneralMediaType::Data(_ft) => Ok(String::new()),
+    };
+    match cid {
+        Err(Error::UnsupportedMediaType(_, _)) | Err(Error::Extraction(_, _)) if tika => {
+            Ok(String::new())
+        }
+        cid => cid,
+    }
+}
+
+//ISCC of a document embedded in the file at `path`, text and metadata come from /rmeta,
+//the data for Data-, Instance- and media Content-IDs from /unpack
+fn generate_embedded(
+    path: &str,
+    document: &TikaDocument,
+    options: &Options,
+    tikaconfig: &TikaConfig,
+    unpacker: &mut Unpacker,
+) -> Result<Iscc, Error> {
+    let file = format!("{}{}", path, document.embedded_path);
+    let data = unpacker.data(&document.embedded_path)?;
+    let source = Source::Memory(&file, &data);
+    let mediatype = get_gmt_from_tika(&document.content_type);
+    let (content, title, mut extra) = mediatype.extract_tika(document, &options.metadata_keys);
+    let mut warnings = Vec::new();
+    let language = match &mediatype {
+        GeneralMediaType::Text(_ft) if !content.trim().is_empty() => {
+            tika_language(tikaconfig, &file, &content, &mut warnings)
+        }
+        _ => None,
+    }
+    .unwrap_or_default();
+    if options.language_extra {
+        extra = with_language(&extra, &language);
+    }
+    let (mid, metatitle, metaextra) = meta_id(&title, &extra);
+    let did = data_id(&source)?;
+    let (iid, tophash) = instance_id(&source)?;
+    // a frame signature belongs to the file, not to embedded videos
+    let cid = content_id(
+        &source,
+        &mediatype,
+        &document.content_type,
+        &content,
+        None,
+        options.partial,
+        true,
+    )?;
+    Ok(Iscc {
+        mid,
+        cid,
+        did,
+        iid,
+        gmt: mediatype.get_gmt_string(),
+        mediatype: document.content_type.to_string(),
+        title: metatitle,
+        extra: metaextra,
+        language,
+        tophash,
+        backend: "tika".to_string(),
+        embedded_path: document.embedded_path.to_string(),
+        children: vec![],
+        warnings,
+        error: String::new(),
+    })
+}
+
+//data of the documents embedded in a file. Tika unpacks one level per request, the file
+//for the documents in it and each embedded container for the documents nested in it.
+struct Unpacker<'a> {
+    source: &'a Source<'a>,
+    tikaconfig: &'a TikaConfig,
+    //paths of all embedded documents, to tell the container of a nested one
+    paths: Vec<&'a str>,
+    //documents by resource name for each unpacked container ("" for the file itself)
+    containers: HashMap<String, Result<HashMap<String, Vec<u8>>, String>>,
+}
+
+impl<'a> Unpacker<'a> {
+    fn new(
+        source: &'a Source<'a>,
+        tikaconfig: &'a TikaConfig,
+        documents: &'a [TikaDocument],
+    ) -> Unpacker<'a> {
+        Unpacker {
+            source,
+            tikaconfig,
+            paths: documents.iter().map(|d| d.embedded_path.as_str()).collect(),
+            containers: HashMap::new(),
+        }
+    }
+
+    //data of the embedded document at `path`, e.g. "/report.zip/notes.txt"
+    fn data(&mut self, path: &str) -> Result<Vec<u8>, Error> {
+        // resource names may contain "/" (e.g. directories in a zip), the container is
+        // the longest embedded document path the path starts with
+        let container = self
+            .paths
+            .iter()
+            .filter(|p| !p.is_empty() && path.starts_with(&format!("{}/", p)))
+            .max_by_key(|p| p.len())
+            .copied()
+            .unwrap_or("");
+        let name = &path[(container.len() + 1).min(path.len())..];
+        if !self.containers.contains_key(container) {
+            let file = format!("{}{}", self.source.name(), container);
+            let documents = match container {
+                "" => self.source.read().map(|data| data.into_owned()),
+                _ => self.data(container),
+            }
+            .and_then(|data| tika::request::unpack(self.tikaconfig, &file, &data))
+            .map_err(|e| e.to_string());
+            self.containers.insert(container.to_string(), documents);
+        }
+        let fail =
+            |cause: String| Error::Extraction(format!("{}{}", self.source.name(), path), cause);
+        match &self.containers[container] {
+            Ok(documents) => documents
+                .get(name)
+                .cloned()
+                .ok_or_else(|| fail("no data from Apache Tika /unpack".to_string())),
+            Err(error) => Err(fail(error.to_string())),
+        }
+    }
+}
+
+//language of a text detected by tika, a failing request doesn't fail the file: if tika
+//fails or doesn't know the language the built-in detector is asked instead
+fn tika_language(
+    tikaconfig: &TikaConfig,
+    path: &str,
+    text: &str,
+    warnings: &mut Vec<String>,
+) -> Option<String> {
+    match tika::request::language(tikaconfig, path, text) {
+        Ok(Some(language)) => Some(language),
+        Ok(None) => extract::language::detect(text),
+        Err(error) => {
+            warnings.push(format!("{}, using the built-in language detector", error));
+            extract::language::detect(text)
+        }
+    }
+}
+
+//extra text with the detected language appended
+fn with_language(extra: &str, language: &str) -> String {
+    match (extra.trim(), language) {
+        (extra, "") => extra.to_string(),
+        ("", language) => language.to_string(),
+        (extra, language) => format!("{} {}", extra, language),
+    }
+}
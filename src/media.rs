@@ -0,0 +1,463 @@
+// Media type detection and content extraction.
+use std::io::Read;
+use std::path::Path;
+
+use crate::error::Error;
+use crate::extract;
+use crate::extract::ooxml::OoxmlDocument;
+use crate::source::Source;
+use crate::tika;
+use crate::tika::request::{MetadataKeys, TikaDocument};
+
+// General media type of a file, holding the media subtype (e.g. `plain`, `png`).
+//
+// `Container` (archives) and `Data` (any other binary) have no Content-ID, their
+// ISCC consists of Meta-, Data- and Instance-ID only.
+#[derive(Debug)]
+pub enum GeneralMediaType {
+    Text(String),
+    Image(String),
+    Audio(String),
+    Video(String),
+    Container(String),
+    Data(String),
+}
+impl GeneralMediaType {
+    // True if the content of this media type is extracted by Apache Tika in Tika mode.
+    pub fn is_tika_extract(&self) -> bool {
+        matches!(self, GeneralMediaType::Text(_ft))
+    }
+
+    // Extract `(text, title, extra)` from a file or data without Apache Tika.
+    pub fn extract(&self, source: &Source) -> Result<(String, String, String), Error> {
+        match self {
+            GeneralMediaType::Text(_ft) if _ft == "pdf" => extract::pdf::extract(source),
+            GeneralMediaType::Text(_ft) if _ft == "epub+zip" => extract::epub::extract(source),
+            GeneralMediaType::Text(_ft) if _ft == "rtf" => extract::rtf::extract(source),
+            GeneralMediaType::Text(_ft)
+                if _ft == "msword" || _ft == "vnd.ms-excel" || _ft == "vnd.ms-powerpoint" =>
+            {
+                extract::ole::extract(source)
+            }
+            GeneralMediaType::Text(_ft) if _ft.starts_with("vnd.oasis.opendocument.") => {
+                extract::odf::extract(source)
+            }
+            GeneralMediaType::Text(_ft)
+                if _ft == "vnd.openxmlformats-officedocument.wordprocessingml.document" =>
+            {
+                extract::ooxml::extract(source, OoxmlDocument::Word)
+            }
+            GeneralMediaType::Text(_ft)
+                if _ft == "vnd.openxmlformats-officedocument.spreadsheetml.sheet" =>
+            {
+                extract::ooxml::extract(source, OoxmlDocument::Excel)
+            }
+            GeneralMediaType::Text(_ft)
+                if _ft == "vnd.openxmlformats-officedocument.presentationml.presentation" =>
+            {
+                extract::ooxml::extract(source, OoxmlDocument::PowerPoint)
+            }
+            _ => self.extract_file(source),
+        }
+    }
+
+    fn extract_file(&self, source: &Source) -> Result<(String, String, String), Error> {
+        match self {
+            GeneralMediaType::Text(_ft) if _ft == "html" => {
+                let width: usize = 72;
+                let contents = html2text::from_read(source.open()?, width);
+                let mut firstline = "";
+                for l in contents.lines() {
+                    if l.trim() != "" {
+                        firstline = l;
+                        break;
+                    }
+                }
+                Ok((contents.to_string(), firstline.to_string(), "".to_string()))
+            }
+            GeneralMediaType::Text(_ft) => {
+                let data = source.read()?;
+                let contents = std::str::from_utf8(&data)
+                    .map_err(|e| Error::Extraction(source.name().to_string(), e.to_string()))?;
+                let mut firstline = "";
+                for l in contents.lines() {
+                    if l.trim() != "" {
+                        firstline = l;
+                        break;
+                    }
+                }
+                Ok((contents.to_string(), firstline.to_string(), "".to_string()))
+            }
+            _ => Ok(("".to_string(), "".to_string(), "".to_string())),
+        }
+    }
+
+    // Extract `(text, title, extra)` from a document parsed by Apache Tika.
+    //
+    // Title and extra are taken from the metadata keys in `keys`, the title falls back
+    // to the first line of text.
+    pub fn extract_tika(
+        &self,
+        document: &TikaDocument,
+        keys: &MetadataKeys,
+    ) -> (String, String, String) {
+        let contents = &document.text;
+        let mut title = tika::request::title(&document.metadata, keys);
+        if title.is_empty() {
+            title = contents
+                .lines()
+                .find(|l| l.trim() != "")
+                .unwrap_or("")
+                .to_string();
+        }
+        let extra = tika::request::extra(&document.metadata, keys);
+        (contents.to_string(), title, extra)
+    }
+
+    // Name of the general media type (`text`, `image`, `audio`, `video`, `container`, `data`).
+    pub fn get_gmt_string(&self) -> String {
+        match self {
+            GeneralMediaType::Text(_ft) => "text".to_string(),
+            GeneralMediaType::Image(_ft) => "image".to_string(),
+            GeneralMediaType::Audio(_ft) => "audio".to_string(),
+            GeneralMediaType::Video(_ft) => "video".to_string(),
+            GeneralMediaType::Container(_ft) => "container".to_string(),
+            GeneralMediaType::Data(_ft) => "data".to_string(),
+        }
+    }
+}
+
+// Detect the media type of a file or data from its content, falling back to the
+// `declared` media type or, if there is none, the file extension.
+//
+// Returns the media type and, if content and file extension disagree, a message
+// naming both. The media type found in the content wins in that case.
+pub fn detect_mimetype(
+    source: &Source,
+    declared: Option<&str>,
+) -> Result<(String, Option<String>), Error> {
+    let file = source.name();
+    let sniffed = sniff_mimetype(source)?;
+    let guessed = declared.or_else(|| guess_mimetype(file));
+    match (sniffed, guessed) {
+        (Some(sniffed), Some(guessed)) if sniffed == guessed => Ok((sniffed, None)),
+        (Some(sniffed), Some(guessed)) if is_refined_by(&sniffed, guessed) => {
+            Ok((guessed.to_string(), None))
+        }
+        (Some(sniffed), Some(guessed)) => {
+            let suggests = match declared {
+                Some(_) => "declared media type is",
+                None => "file-extension suggests",
+            };
+            let mismatch = format!(
+                "{} -- Content is {}, but {} {}",
+                file, sniffed, suggests, guessed
+            );
+            Ok((sniffed, Some(mismatch)))
+        }
+        (Some(sniffed), None) => Ok((sniffed, None)),
+        (None, Some(guessed)) => Ok((guessed.to_string(), None)),
+        (None, None) => Err(Error::UnknownExtension(file.to_string())),
+    }
+}
+
+//media types for extensions unknown to mime_guess
+const EXTENSIONS: &[(&str, &str)] = &[
+    ("y4m", "video/x-yuv4mpeg"),
+    ("mjpeg", "video/x-motion-jpeg"),
+    ("mjpg", "video/x-motion-jpeg"),
+];
+
+//media type from the file extension
+fn guess_mimetype(file: &str) -> Option<&'static str> {
+    let extension = Path::new(file).extension()?.to_str()?.to_ascii_lowercase();
+    EXTENSIONS
+        .iter()
+        .find(|(e, _)| *e == extension)
+        .map(|(_, mimetype)| *mimetype)
+        .or_else(|| mime_guess::from_ext(&extension).first_raw())
+}
+
+//number of leading bytes inspected by sniff_mimetype
+const SNIFF_LENGTH: u64 = 8192;
+
+// Detect the media type of a file or data from its leading bytes (magic numbers).
+//
+// Recognizes PNG, GIF, JPEG, TIFF, WebP, BMP, AVI, Y4M, WAV, AIFF, FLAC, Ogg, MP3, PDF,
+// RTF, OLE2 (doc, xls, ppt), ZIP (including OOXML, OpenDocument and EPUB) and UTF-8 text.
+// Returns `None` for anything else.
+pub fn sniff_mimetype(source: &Source) -> Result<Option<String>, Error> {
+    let mut head = Vec::new();
+    source
+        .open()?
+        .take(SNIFF_LENGTH)
+        .read_to_end(&mut head)
+        .map_err(|e| source.io(e))?;
+    let mimetype = if head.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if head.starts_with(b"GIF87a") || head.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if head.starts_with(b"\xff\xd8\xff") {
+        Some("image/jpeg")
+    } else if head.starts_with(b"II*\x00") || head.starts_with(b"MM\x00*") {
+        Some("image/tiff")
+    } else if head.starts_with(b"RIFF") && head.get(8..12) == Some(b"WEBP") {
+        Some("image/webp")
+    } else if is_bmp(&head) {
+        Some("image/bmp")
+    } else if head.starts_with(b"RIFF") && head.get(8..12) == Some(b"AVI ") {
+        Some("video/x-msvideo")
+    } else if head.starts_with(b"YUV4MPEG2 ") {
+        Some("video/x-yuv4mpeg")
+    } else if head.starts_with(b"RIFF") && head.get(8..12) == Some(b"WAVE") {
+        Some("audio/wav")
+    } else if head.starts_with(b"FORM") && matches!(head.get(8..12), Some(b"AIFF") | Some(b"AIFC"))
+    {
+        Some("audio/aiff")
+    } else if head.starts_with(b"fLaC") {
+        Some("audio/flac")
+    } else if head.starts_with(b"OggS") {
+        Some("audio/ogg")
+    } else if head.starts_with(b"ID3") || is_mpeg_audio(&head) {
+        Some("audio/mpeg")
+    } else if head.starts_with(b"%PDF-") {
+        Some("application/pdf")
+    } else if head.starts_with(b"{\\rtf") {
+        Some("application/rtf")
+    } else if head.starts_with(b"\xd0\xcf\x11\xe0\xa1\xb1\x1a\xe1") {
+        Some(sniff_ole(source))
+    } else if head.starts_with(b"PK\x03\x04") {
+        return Ok(Some(sniff_zip(source)));
+    } else {
+        sniff_text(&head)
+    };
+    Ok(mimetype.map(String::from))
+}
+
+//BMP file header: reserved bytes zero and the size of a known DIB header
+//(BITMAPCOREHEADER, BITMAPINFOHEADER and its versions up to BITMAPV5HEADER)
+fn is_bmp(head: &[u8]) -> bool {
+    let dib_size = head
+        .get(14..18)
+        .map(|s| u32::from_le_bytes([s[0], s[1], s[2], s[3]]));
+    head.starts_with(b"BM")
+        && head.get(6..10) == Some(&[0; 4])
+        && matches!(dib_size, Some(12 | 40 | 52 | 56 | 64 | 108 | 124))
+}
+
+//MPEG audio frame header (layer I-III, not ADTS): frame sync, no reserved version, layer,
+//bitrate or sample rate. FF FE and FF FF (layer I) are left out, FF FE is the byte order
+//mark of UTF-16LE text.
+fn is_mpeg_audio(head: &[u8]) -> bool {
+    matches!(head, [0xff, b, c, ..]
+        if b & 0xe0 == 0xe0
+            && b & 0x18 != 0x08
+            && b & 0x06 != 0
+            && *b < 0xfe
+            && c & 0xf0 != 0xf0
+            && c & 0x0c != 0x0c)
+}
+
+//media type of an OLE2 compound file from its streams (doc, xls, ppt)
+fn sniff_ole(source: &Source) -> &'static str {
+    source
+        .open()
+        .ok()
+        .and_then(|reader| cfb::CompoundFile::open(reader).ok())
+        .and_then(|ole| extract::ole::document_type(&ole))
+        .map_or("application/x-ole-storage", |document| document.mimetype())
+}
+
+//media type of a zip container from its mimetype entry (OpenDocument, EPUB)
+//or the names of its entries (OOXML)
+fn sniff_zip(source: &Source) -> String {
+    let archive = source
+        .open()
+        .ok()
+        .and_then(|reader| zip::ZipArchive::new(reader).ok());
+    let mut archive = match archive {
+        Some(archive) => archive,
+        None => return "application/zip".to_string(),
+    };
+    let mut declared = String::new();
+    if let Ok(entry) = archive.by_name("mimetype") {
+        let _ = entry.take(128).read_to_string(&mut declared);
+    }
+    let declared = declared.trim();
+    if declared.starts_with("application/") && !declared.contains(char::is_whitespace) {
+        return declared.to_string();
+    }
+    let has = |name: &str| archive.file_names().any(|n| n == name);
+    let mimetype = if has("word/document.xml") {
+        "application/vnd.openxmlformats-officedocument.wordprocessingml.document"
+    } else if has("xl/workbook.xml") {
+        "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"
+    } else if has("ppt/presentation.xml") {
+        "application/vnd.openxmlformats-officedocument.presentationml.presentation"
+    } else {
+        "application/zip"
+    };
+    mimetype.to_string()
+}
+
+//text/plain, text/html or text/xml for valid UTF-8 without NUL bytes
+fn sniff_text(head: &[u8]) -> Option<&'static str> {
+    if head.is_empty() || head.contains(&0) {
+        return None;
+    }
+    let text = match std::str::from_utf8(head) {
+        Ok(text) => text,
+        // the last character may be cut off at SNIFF_LENGTH
+        Err(e) if e.error_len().is_none() => std::str::from_utf8(&head[..e.valid_up_to()]).ok()?,
+        Err(_) => return None,
+    };
+    let start = text.trim_start_matches('\u{feff}').trim_start();
+    let lower = start.get(..14).unwrap_or(start).to_ascii_lowercase();
+    if lower.starts_with("<!doctype html") || lower.starts_with("<html") {
+        Some("text/html")
+    } else if lower.starts_with("<?xml") {
+        Some("text/xml")
+    } else {
+        Some("text/plain")
+    }
+}
+
+//true if the sniffed media type is a generic form of the one guessed from the extension
+fn is_refined_by(sniffed: &str, guessed: &str) -> bool {
+    match sniffed {
+        "text/plain" | "text/html" | "text/xml" => {
+            guessed.starts_with("text/")
+                || guessed.ends_with("+xml")
+                || guessed == "application/xml"
+                || guessed == "application/json"
+                || guessed == "application/javascript"
+        }
+        "application/x-ole-storage" => {
+            guessed == "application/msword" || guessed.starts_with("application/vnd.ms-")
+        }
+        // a raw MJPEG stream starts with its first JPEG frame
+        "image/jpeg" => guessed == "video/x-motion-jpeg",
+        "application/zip" => {
+            guessed.ends_with("+zip") || guessed.starts_with("application/vnd.oasis.opendocument.")
+        }
+        _ => false,
+    }
+}
+
+//application/* media types with standalone text extraction
+const TEXT_DOCUMENTS: &[&str] = &[
+    "pdf",
+    "epub+zip",
+    "rtf",
+    "msword",
+    "vnd.ms-excel",
+    "vnd.ms-powerpoint",
+    "vnd.openxmlformats-officedocument.wordprocessingml.document",
+    "vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+    "vnd.openxmlformats-officedocument.presentationml.presentation",
+    "vnd.oasis.opendocument.text",
+    "vnd.oasis.opendocument.spreadsheet",
+    "vnd.oasis.opendocument.presentation",
+];
+
+// Map a media type to its general media type.
+pub fn get_gmt_from_mimetype(file: &str, mimetype: &str) -> Result<GeneralMediaType, Error> {
+    let mut parts = mimetype.split('/');
+    let gmt = parts.next().unwrap_or("");
+    let ft = parts.next().unwrap_or("");
+    match gmt {
+        "text" => Ok(GeneralMediaType::Text(String::from(ft))),
+        "application" if TEXT_DOCUMENTS.contains(&ft) => {
+            Ok(GeneralMediaType::Text(String::from(ft)))
+        }
+        "image" => Ok(GeneralMediaType::Image(String::from(ft))),
+        "audio" => Ok(GeneralMediaType::Audio(String::from(ft))),
+        "video" => Ok(GeneralMediaType::Video(String::from(ft))),
+        _ => Err(Error::UnsupportedMediaType(
+            file.to_string(),
+            mimetype.to_string(),
+        )),
+    }
+}
+
+//application/* media types tika extracts text from, in addition to TEXT_DOCUMENTS
+const TIKA_TEXT_DOCUMENTS: &[&str] = &[
+    "xml",
+    "json",
+    "javascript",
+    "mbox",
+    "x-tika-msoffice",
+    "x-tika-ooxml",
+    "vnd.ms-outlook",
+    "vnd.ms-word.document.macroenabled.12",
+    "vnd.ms-excel.sheet.macroenabled.12",
+    "vnd.ms-powerpoint.presentation.macroenabled.12",
+    "vnd.ms-excel.sheet.binary.macroenabled.12",
+    "vnd.apple.pages",
+    "vnd.apple.numbers",
+    "vnd.apple.keynote",
+    "vnd.wordperfect",
+    "x-mobipocket-ebook",
+    "x-fictionbook+xml",
+    "x-latex",
+    "x-tex",
+];
+
+//media type prefixes of text documents, e.g. all OpenDocument and OOXML formats
+const TIKA_TEXT_PREFIXES: &[&str] = &[
+    "application/vnd.oasis.opendocument.",
+    "application/vnd.openxmlformats-officedocument.",
+    "message/",
+];
+
+//archives and other containers of files
+const TIKA_CONTAINERS: &[&str] = &[
+    "application/zip",
+    "application/x-tar",
+    "application/gzip",
+    "application/x-gzip",
+    "application/x-bzip",
+    "application/x-bzip2",
+    "application/x-xz",
+    "application/x-lzma",
+    "application/zstd",
+    "application/x-compress",
+    "application/x-7z-compressed",
+    "application/x-rar-compressed",
+    "application/vnd.rar",
+    "application/x-archive",
+    "application/x-cpio",
+    "application/java-archive",
+    "application/x-iso9660-image",
+    "application/vnd.ms-cab-compressed",
+    "application/x-apple-diskimage",
+];
+
+// Map the media type detected by Apache Tika to its general media type.
+//
+// Text and documents Tika extracts text from map to `Text`, archives to `Container`
+// and any other media type (e.g. `application/octet-stream`, executables, fonts) to
+// `Data`.
+pub fn get_gmt_from_tika(mimetype: &str) -> GeneralMediaType {
+    let mut parts = mimetype.split('/');
+    let gmt = parts.next().unwrap_or("");
+    let ft = String::from(parts.next().unwrap_or(""));
+    let is_text = gmt == "text"
+        || (gmt == "application"
+            && (TEXT_DOCUMENTS.contains(&ft.as_str())
+                || TIKA_TEXT_DOCUMENTS.contains(&ft.as_str())
+                || ft.ends_with("+xml")))
+        || TIKA_TEXT_PREFIXES.iter().any(|p| mimetype.starts_with(p));
+    if is_text {
+        return GeneralMediaType::Text(ft);
+    }
+    if TIKA_CONTAINERS.contains(&mimetype) {
+        return GeneralMediaType::Container(ft);
+    }
+    match gmt {
+        "image" => GeneralMediaType::Image(ft),
+        "audio" => GeneralMediaType::Audio(ft),
+        "video" => GeneralMediaType::Video(ft),
+        _ => GeneralMediaType::Data(ft),
+    }
+}
@@ -0,0 +1,147 @@
+// Input of the ISCC pipeline: a file or data held in memory (e.g. read from stdin).
+//
+// Also computes the components that only depend on the raw data, Data-ID and
+// Instance-ID, from a [`Source`] with the algorithms of iscc-rs.
+use std::borrow::Cow;
+use std::fs::{self, File};
+use std::io::{self, Cursor, Read, Seek};
+
+use iscc::base58;
+use iscc::did::chunk_length;
+use iscc::hashes::{minimum_hash, xxhash32};
+use iscc::iid::{sha256d, top_hash};
+
+use crate::error::Error;
+
+// A file or named data in memory to generate an ISCC for.
+#[derive(Debug, Clone, Copy)]
+pub enum Source<'a> {
+    // File at a path.
+    File(&'a str),
+    // Data in memory with a name for messages, e.g. `-` for stdin.
+    Memory(&'a str, &'a [u8]),
+}
+
+// Seekable reader of a [`Source`].
+pub trait ReadSeek: Read + Seek {}
+
+impl<T: Read + Seek> ReadSeek for T {}
+
+impl<'a> From<&'a str> for Source<'a> {
+    fn from(path: &'a str) -> Source<'a> {
+        Source::File(path)
+    }
+}
+
+impl<'a> Source<'a> {
+    // Path of the file or name of the data, as used in messages.
+    pub fn name(&self) -> &'a str {
+        match self {
+            Source::File(path) => path,
+            Source::Memory(name, _) => name,
+        }
+    }
+
+    // Reader for the data.
+    pub fn open(&self) -> Result<Box<dyn ReadSeek + Send + 'a>, Error> {
+        match self {
+            Source::File(path) => Ok(Box::new(File::open(path).map_err(|e| self.io(e))?)),
+            Source::Memory(_, data) => Ok(Box::new(Cursor::new(*data))),
+        }
+    }
+
+    // All data, read from the file or borrowed.
+    pub fn read(&self) -> Result<Cow<'a, [u8]>, Error> {
+        match self {
+            Source::File(path) => Ok(Cow::Owned(fs::read(path).map_err(|e| self.io(e))?)),
+            Source::Memory(_, data) => Ok(Cow::Borrowed(data)),
+        }
+    }
+
+    // I/O error for this source.
+    pub fn io(&self, error: io::Error) -> Error {
+        Error::Io(self.name().to_string(), error)
+    }
+}
+
+//chunking parameters of the Data-ID, small chunks for the first CHUNKS_SMALL chunks
+const CHUNKS_SMALL: usize = 100;
+const GEAR1: (usize, usize, usize, u64, u64) = (40, 20, 640, 0x0001_6118, 0x0000_A0B1);
+const GEAR2: (usize, usize, usize, u64, u64) = (
+    4096,
+    2048,
+    65536,
+    0x0003_5907_0353_0000,
+    0x0000_D900_0353_0000,
+);
+
+// Component Header
+const HEAD_DID: u8 = 0x20;
+const HEAD_IID: u8 = 0x30;
+
+//read until `buffer` holds `size` bytes or the data ends
+fn fill(reader: &mut impl Read, buffer: &mut Vec<u8>, size: usize) -> io::Result<()> {
+    if buffer.len() < size {
+        let missing = (size - buffer.len()) as u64;
+        reader.take(missing).read_to_end(buffer)?;
+    }
+    Ok(())
+}
+
+// Data-ID of the data: MinHash of the xxHash32 of content defined chunks.
+pub fn data_id(source: &Source) -> Result<String, Error> {
+    let mut reader = source.open()?;
+    let mut section = Vec::new();
+    let mut features = Vec::new();
+    loop {
+        let (norm, min, max, mask1, mask2) = if features.len() < CHUNKS_SMALL {
+            GEAR1
+        } else {
+            GEAR2
+        };
+        fill(&mut reader, &mut section, max).map_err(|e| source.io(e))?;
+        if section.is_empty() {
+            break;
+        }
+        let boundary = chunk_length(&section, norm, min, max, mask1, mask2);
+        features.push(xxhash32(&section[..boundary]));
+        section.drain(..boundary);
+    }
+    // empty data is a single empty chunk
+    if features.is_empty() {
+        features.push(xxhash32(&[]));
+    }
+    let mut digest = vec![HEAD_DID];
+    for bits in minimum_hash(features).chunks(8) {
+        digest.push(bits.iter().fold(0, |byte, x| byte << 1 | (x & 1) as u8));
+    }
+    Ok(base58::encode(&digest))
+}
+
+//size of the leaves of the Instance-ID hash tree
+const LEAF_SIZE: usize = 20 * 1024 * 1024;
+
+// Instance-ID and hex encoded tophash of the data: merkle root of the SHA256d of its chunks.
+pub fn instance_id(source: &Source) -> Result<(String, String), Error> {
+    let mut reader = source.open()?;
+    let mut leaves = Vec::new();
+    let mut chunk = Vec::new();
+    loop {
+        chunk.clear();
+        chunk.push(0);
+        fill(&mut reader, &mut chunk, LEAF_SIZE + 1).map_err(|e| source.io(e))?;
+        // empty data is a single empty leaf
+        if chunk.len() == 1 && !leaves.is_empty() {
+            break;
+        }
+        leaves.push(sha256d(&chunk));
+        if chunk.len() <= LEAF_SIZE {
+            break;
+        }
+    }
+    let tophash = top_hash(&leaves);
+    let mut digest = vec![HEAD_IID];
+    digest.extend(&tophash[..8]);
+    let hex = tophash.iter().map(|b| format!("{:02x}", b)).collect();
+    Ok((base58::encode(&digest), hex))
+}
@@ -1 +1,5 @@
-pub mod request;
+//! Connection to an Apache Tika server, used for media type detection and text
+//! extraction in Tika mode (see [`Options::tika`](crate::Options::tika)).
+pub(crate) mod request;
+
+pub use request::{check, config, MetadataKeys, TikaConfig, TikaOptions};
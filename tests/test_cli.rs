@@ -77,6 +77,133 @@ fn test_xlsx_file() -> Result<(), Box<dyn std::error::Error>> {
 }
 
 //Image files
+#[test]
+fn test_odt_file() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.arg("gen")
+        .arg("-f")
+        .arg("./tests/test_data/text/demo.odt");
+    cmd.assert().success().stdout(predicate::str::contains(
+        "CTMjk4o5H96BV-CDYmhn8bqc6Pi-CRfaTtW6M6YWX",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_ods_file() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.arg("gen")
+        .arg("-f")
+        .arg("./tests/test_data/text/demo.ods");
+    cmd.assert().success().stdout(predicate::str::contains(
+        "CTcFSR63wuPDc-CDcnGEYFqeiw5-CR2EWbFNpghYN",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_odp_file() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.arg("gen")
+        .arg("-f")
+        .arg("./tests/test_data/text/demo.odp");
+    cmd.assert().success().stdout(predicate::str::contains(
+        "CTcFSR63wuPDc-CDgtZbVpMxXPr-CRGR6CWNgbFW1",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_epub_file() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.arg("gen")
+        .arg("-o")
+        .arg("json")
+        .arg("-f")
+        .arg("./tests/test_data/text/demo.epub");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(r#""mid":"CCh2TW8YiFhSN""#))
+        .stdout(predicate::str::contains(r#""cid":"CTMUwFzJSXaFW""#))
+        .stdout(predicate::str::contains(r#""gmt":"text""#))
+        .stdout(predicate::str::contains(
+            r#""mediatype":"application/epub+zip""#,
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn test_rtf_file() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.arg("gen")
+        .arg("-f")
+        .arg("./tests/test_data/text/demo.rtf");
+    cmd.assert().success().stdout(predicate::str::contains(
+        "CTMjk4o5H96BV-CD57JTpJhc96h-CRahMGRh56P2x",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_doc_file() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.arg("gen")
+        .arg("-f")
+        .arg("./tests/test_data/text/demo.doc");
+    cmd.assert().success().stdout(predicate::str::contains(
+        "CTMjk4o5H96BV-CDM6E14HcCZjQ-CR1LUvGDVrWye",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_pdf_file() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.arg("gen")
+        .arg("-f")
+        .arg("./tests/test_data/text/demo.pdf");
+    cmd.assert().success().stdout(predicate::str::contains(
+        "CTvS3JDAS22eY-CDcRsq2Wu1x8N-CRNTcF9x6S3XC",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_broken_pdf_file() -> Result<(), Box<dyn std::error::Error>> {
+    // a page without MediaBox makes the pdf parser panic
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.arg("gen")
+        .arg("-f")
+        .arg("./tests/test_data/text/broken.pdf");
+    cmd.assert()
+        .code(6)
+        .stderr(predicate::str::contains("Extraction failed: panicked"));
+    let dir = tempfile::tempdir()?;
+    std::fs::copy(
+        "./tests/test_data/text/broken.pdf",
+        dir.path().join("a.pdf"),
+    )?;
+    std::fs::copy("./tests/test_data/text/demo.pdf", dir.path().join("b.pdf"))?;
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    // the only worker goes on with the next file
+    cmd.arg("batch")
+        .arg("-j")
+        .arg("1")
+        .arg("-d")
+        .arg(dir.path());
+    cmd.assert()
+        .code(2)
+        .stdout(predicate::str::contains("CTvS3JDAS22eY-CDcRsq2Wu1x8N"));
+    Ok(())
+}
+
 #[test]
 fn test_png_file() -> Result<(), Box<dyn std::error::Error>> {
     let mut cmd = Command::cargo_bin("iscc-cli")?;
@@ -90,18 +217,18 @@ fn test_png_file() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-/*#[test]
+#[test]
 fn test_jpg_file() -> Result<(), Box<dyn std::error::Error>> {
     let mut cmd = Command::cargo_bin("iscc-cli")?;
     cmd.arg("gen")
         .arg("-f")
         .arg("./tests/test_data/image/demo.jpg");
-    cmd.assert()
-        .success()
-        .stdout(predicate::str::contains("CYDfTq7Qc7Fre-CDYkLqqmQJaQk-CRAPu5NwQgAhv"));
+    cmd.assert().success().stdout(predicate::str::contains(
+        "CYDfTq7Qc7Fre-CDYkLqqmQJaQk-CRAPu5NwQgAhv",
+    ));
 
     Ok(())
-}*/
+}
 
 #[test]
 fn test_gif_file() -> Result<(), Box<dyn std::error::Error>> {
@@ -116,18 +243,180 @@ fn test_gif_file() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-/*#[test]
+#[test]
 fn test_tif_file() -> Result<(), Box<dyn std::error::Error>> {
     let mut cmd = Command::cargo_bin("iscc-cli")?;
     cmd.arg("gen")
         .arg("-f")
         .arg("./tests/test_data/image/demo.tif");
+    cmd.assert().success().stdout(predicate::str::contains(
+        "CYDfTq7Qc7Fre-CD29F7ZTmuBGJ-CRbzCG9h5EktB",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_webp_file() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.arg("gen")
+        .arg("-f")
+        .arg("./tests/test_data/image/demo.webp");
+    cmd.assert().success().stdout(predicate::str::contains(
+        "CYDfTq7Qc7Fre-CDh7HKyrWhXvU-CRAjWag6S8R3q",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_bmp_file() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.arg("gen")
+        .arg("-f")
+        .arg("./tests/test_data/image/demo.bmp");
+    cmd.assert().success().stdout(predicate::str::contains(
+        "CYDfTq7Qc7Fre-CDEFoVWjmfZb9-CR3mzWdueJSgZ",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_jpg_exif_orientation_file() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.arg("gen")
+        .arg("-f")
+        .arg("./tests/test_data/image/demo_rotated.jpg");
+    cmd.assert().success().stdout(predicate::str::contains(
+        "CYDfTq7Qc7Fre-CDXoYLLfxP1PK-CRtDAtZyUCfCs",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_wav_file() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.arg("gen")
+        .arg("-f")
+        .arg("./tests/test_data/audio/demo.wav");
+    cmd.assert().success().stdout(predicate::str::contains(
+        "CAtkLpXrp79C4-CD9C546djwUvG-CRU8RLi9hJZsk",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_aif_file() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.arg("gen")
+        .arg("-f")
+        .arg("./tests/test_data/audio/demo.aif");
+    cmd.assert().success().stdout(predicate::str::contains(
+        "CAtkLpXrp79C4-CDEt2GqU9tFE8-CRgADn3twknM7",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_mp3_file() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.arg("gen")
+        .arg("-f")
+        .arg("./tests/test_data/audio/demo.mp3");
+    cmd.assert().success().stdout(predicate::str::contains(
+        "CAtkLpXrp748d-CDjNkzT4vbC6L-CRHpRRnHj7mf7",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_ogg_file() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.arg("gen")
+        .arg("-f")
+        .arg("./tests/test_data/audio/demo.ogg");
+    cmd.assert().success().stdout(predicate::str::contains(
+        "CAtkLpXrp79C4-CDTELon4aRWGq-CRmrvmdTkN7Yw",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_y4m_file() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.arg("gen")
+        .arg("-f")
+        .arg("./tests/test_data/video/demo.y4m");
+    cmd.assert().success().stdout(predicate::str::contains(
+        "CVMrjgYV4w2gW-CDvaUzMRMkAZ8-CR897ZnPN8uus",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_avi_file() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.arg("gen")
+        .arg("-f")
+        .arg("./tests/test_data/video/demo.avi");
+    cmd.assert().success().stdout(predicate::str::contains(
+        "CVMrjgYV4w2gW-CD9PWSHUhWhjd-CRX3iRzPDExD4",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_mjpeg_file() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.arg("gen")
+        .arg("-f")
+        .arg("./tests/test_data/video/demo.mjpeg");
+    cmd.assert().success().stdout(predicate::str::contains(
+        "CVaH5yf1nPGoK-CDWQEZ3YXVGsR-CRPnhJbPeuXNQ",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_frame_sig() -> Result<(), Box<dyn std::error::Error>> {
+    // XML and binary output of ffmpeg's signature filter hold the same frames
+    for sig in &["signature.xml", "signature.bin"] {
+        let mut cmd = Command::cargo_bin("iscc-cli")?;
+        cmd.arg("gen")
+            .arg("-f")
+            .arg("./tests/test_data/video/master.3gp")
+            .arg("--frame-sig")
+            .arg(format!("./tests/test_data/video/{}", sig));
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::contains("-CV44yxG2uoCSa-"));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_sim_audio() -> Result<(), Box<dyn std::error::Error>> {
+    // Content-IDs of demo.wav and demo.mp3
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.arg("sim")
+        .arg("-a")
+        .arg("CAtkLpXrp79C4")
+        .arg("-b")
+        .arg("CAtkLpXrp748d");
     cmd.assert()
         .success()
-        .stdout(predicate::str::contains("CYDfTq7Qc7Fre-CD29F7ZTmuBGJ-CR5tcBY8co9QS"));
-
+        .stdout(predicate::str::contains("Estimated Similarity: 95.31"));
     Ok(())
-}*/
+}
 
 #[test]
 fn test_batch() -> Result<(), Box<dyn std::error::Error>> {
@@ -136,14 +425,19 @@ fn test_batch() -> Result<(), Box<dyn std::error::Error>> {
         .arg("-r")
         .arg("-d")
         .arg("./tests/test_data");
+    // test_data contains files without Content-ID support
     cmd.assert()
-        .success()
+        .code(2)
         .stdout(predicate::str::contains(
             "CTMjk4o5H96BV-CD6XL9SFyWgsW-CR28vgw3inZGw",
         ))
         .stdout(predicate::str::contains(
             "CYDfTq7Qc7Fre-CDij3vGU1BkCZ-CRNssh4Qc1x5B",
-        ));
+        ))
+        // the language ends the detail line
+        .stdout(predicate::str::is_match(
+            r"(?m)^ISCC:.*,demo\.pdf,text,,en$",
+        )?);
     Ok(())
 }
 #[test]
@@ -159,3 +453,423 @@ fn test_sim() -> Result<(), Box<dyn std::error::Error>> {
         .stdout(predicate::str::contains("Estimated Similarity: 51.56"));
     Ok(())
 }
+
+#[test]
+fn test_gen_json() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.arg("gen")
+        .arg("-o")
+        .arg("json")
+        .arg("-f")
+        .arg("./tests/test_data/image/demo.png");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::starts_with("{"))
+        .stdout(predicate::str::contains(
+            "\"iscc\":\"ISCC:CCh2TW8YiFhSN-CYDfTq7Qc7Fre-CDij3vGU1BkCZ-CRNssh4Qc1x5B\"",
+        ))
+        .stdout(predicate::str::contains(
+            "\"file\":\"./tests/test_data/image/demo.png\"",
+        ))
+        .stdout(predicate::str::contains("\"gmt\":\"image\""));
+    Ok(())
+}
+
+#[test]
+fn test_gen_language() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.arg("gen")
+        .arg("-o")
+        .arg("json")
+        .arg("--lang-extra")
+        .arg("-e")
+        .arg("Draft")
+        .arg("-f")
+        .arg("./tests/test_data/text/demo.txt");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("\"extra\":\"draft la\""))
+        .stdout(predicate::str::contains("\"language\":\"la\""));
+    Ok(())
+}
+
+#[test]
+fn test_gen_fallback_without_tika() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.arg("--fallback")
+        .arg("tika")
+        .arg("--tika-url")
+        .arg("http://127.0.0.1:1")
+        .arg("gen")
+        .arg("-o")
+        .arg("json")
+        .arg("-f")
+        .arg("./tests/test_data/image/demo.png");
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains("using standalone extraction only"))
+        .stdout(predicate::str::contains("\"backend\":\"standalone\""));
+    Ok(())
+}
+
+#[test]
+fn test_gen_embedded_without_tika() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.arg("gen")
+        .arg("--embedded")
+        .arg("-f")
+        .arg("./tests/test_data/text/demo.docx");
+    cmd.assert()
+        .code(1)
+        .stderr(predicate::str::contains("--embedded needs Apache Tika"));
+    Ok(())
+}
+
+#[test]
+fn test_batch_json() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.arg("batch")
+        .arg("-o")
+        .arg("json")
+        .arg("-d")
+        .arg("./tests/test_data/image");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::is_match(
+            r#"(?m)^\{"file":".*demo\.png",.*\}$"#,
+        )?)
+        .stdout(predicate::str::contains("\"did\":\"CDbAK5Ut4xC69\""));
+    Ok(())
+}
+
+#[test]
+fn test_info() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.arg("info")
+        .arg("ISCC:CCh2TW8YiFhSN-CYDfTq7Qc7Fre-CDij3vGU1BkCZ-CRNssh4Qc1x5B");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::is_match(
+            r"Meta-ID\s+CCh2TW8YiFhSN\s+0x00\s+-\s+ef46db3751d8e999",
+        )?)
+        .stdout(predicate::str::is_match(
+            r"Content-ID-Image\s+CYDfTq7Qc7Fre\s+0x12\s+false\s+c343309e3c9e8e67",
+        )?)
+        .stdout(predicate::str::contains("Data-ID"))
+        .stdout(predicate::str::contains("Instance-ID"));
+    Ok(())
+}
+
+#[test]
+fn test_info_invalid() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.arg("decode").arg("CDij3vGU1BkC0");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid character '0'"));
+    Ok(())
+}
+
+#[test]
+fn test_sim_invalid() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.arg("sim")
+        .arg("-a")
+        .arg("CDcRsq2Wu1x")
+        .arg("-b")
+        .arg("CDij3vGU1BkCZ");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("must be 13 characters long"));
+
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.arg("sim")
+        .arg("-a")
+        .arg("CDcRsq2Wu1x8l")
+        .arg("-b")
+        .arg("CDij3vGU1BkCZ");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid character 'l'"));
+    Ok(())
+}
+
+#[test]
+fn test_sim_prefixed_component() -> Result<(), Box<dyn std::error::Error>> {
+    // a single component with the ISCC: prefix is no full code
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.arg("sim")
+        .arg("-a")
+        .arg("ISCC:CDcRsq2Wu1x8N")
+        .arg("-b")
+        .arg("CDij3vGU1BkCZ");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Estimated Similarity: 51.56"));
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.arg("info").arg("iscc:CYDfTq7Qc7Fre");
+    cmd.assert().success().stdout(predicate::str::is_match(
+        r"Content-ID-Image\s+CYDfTq7Qc7Fre\s+0x12\s+false\s+c343309e3c9e8e67",
+    )?);
+    Ok(())
+}
+
+#[test]
+fn test_sim_type_mismatch() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.arg("sim")
+        .arg("-a")
+        .arg("CYDfTq7Qc7Fre")
+        .arg("-b")
+        .arg("CDij3vGU1BkCZ");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "Cannot compare Content-ID-Image (CYDfTq7Qc7Fre) with Data-ID (CDij3vGU1BkCZ)",
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_sim_full_code() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.arg("sim")
+        .arg("-a")
+        .arg("ISCC:CCh2TW8YiFhSN-CYDfTq7Qc7Fre-CDij3vGU1BkCZ-CRNssh4Qc1x5B")
+        .arg("-b")
+        .arg("CCh2TW8YiFhSN-CYDk9BdiCqKoa-CDYkLqqmQJaQk-CRAPu5NwQgAhv");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::is_match(
+            r"Content-ID-Image\s+CYDfTq7Qc7Fre\s+CYDk9BdiCqKoa\s+2\s+96.88",
+        )?)
+        .stdout(predicate::str::is_match(
+            r"Data-ID\s+\S+\s+\S+\s+36\s+43.75",
+        )?)
+        .stdout(predicate::str::is_match(
+            r"Instance-ID\s+\S+\s+\S+\s+-\s+no match",
+        )?)
+        .stdout(predicate::str::contains("Verdict: similar"));
+    Ok(())
+}
+
+#[test]
+fn test_sim_without_content_id() -> Result<(), Box<dyn std::error::Error>> {
+    // the docx parsed as zip container by Apache Tika and standalone as text
+    let container = "ISCC:CCh2TW8YiFhSN-CD6XL9SFyWgsW-CR28vgw3inZGw";
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.args(["sim", "-a", container, "-b", container]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Content-ID").not())
+        .stdout(predicate::str::contains("Verdict: identical"));
+
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.args([
+        "sim",
+        "-a",
+        container,
+        "-b",
+        "CCh2TW8YiFhSN-CTMjk4o5H96BV-CD6XL9SFyWgsW-CR28vgw3inZGw",
+    ]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::is_match(
+            r"Content-ID-Text\s+-\s+CTMjk4o5H96BV\s+-\s+not comparable",
+        )?)
+        .stdout(predicate::str::contains("Verdict: identical"));
+
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.args(["info", container]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Content-ID").not())
+        .stdout(predicate::str::contains("Instance-ID"));
+    Ok(())
+}
+
+#[test]
+fn test_batch_jobs() -> Result<(), Box<dyn std::error::Error>> {
+    let serial = Command::cargo_bin("iscc-cli")?
+        .args(["batch", "-r", "-j", "1", "-d", "./tests/test_data"])
+        .output()?;
+    let parallel = Command::cargo_bin("iscc-cli")?
+        .args(["batch", "-r", "-j", "8", "-d", "./tests/test_data"])
+        .output()?;
+    assert_eq!(serial.status.code(), Some(2));
+    assert_eq!(parallel.status.code(), Some(2));
+    assert!(!serial.stdout.is_empty());
+    assert_eq!(serial.stdout, parallel.stdout);
+    Ok(())
+}
+
+#[test]
+fn test_gen_csv() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.arg("gen")
+        .arg("-o")
+        .arg("csv")
+        .arg("-f")
+        .arg("./tests/test_data/image/demo.png");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "path,mediatype,gmt,iscc,mid,cid,did,iid,title,language,backend,tophash,error\n",
+        ))
+        .stdout(predicate::str::contains(
+            "./tests/test_data/image/demo.png,image/png,image,ISCC:",
+        ))
+        .stdout(predicate::str::contains(
+            ",CYDfTq7Qc7Fre,CDij3vGU1BkCZ,CRNssh4Qc1x5B,",
+        ));
+    Ok(())
+}
+
+#[test]
+fn test_batch_csv_quoting() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    std::fs::copy(
+        "./tests/test_data/image/demo.png",
+        dir.path().join("demo, \"copy\".png"),
+    )?;
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.arg("batch")
+        .arg("-o")
+        .arg("csv")
+        .arg("-d")
+        .arg(dir.path());
+    cmd.assert().success().stdout(predicate::str::contains(
+        "\"demo, \"\"copy\"\".png\",image/png,image,ISCC:",
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_batch_csv() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.arg("batch")
+        .arg("-r")
+        .arg("-o")
+        .arg("csv")
+        .arg("-d")
+        .arg("./tests/test_data");
+    cmd.assert()
+        .code(2)
+        .stdout(predicate::str::contains(
+            "image/demo.gif,image/gif,image,ISCC:",
+        ))
+        .stdout(predicate::str::contains(
+            "audio/demo.wav,audio/wav,audio,ISCC:",
+        ))
+        .stdout(predicate::str::is_match(
+            r"(?m)^video/master\.3gp,,,,,,,,,,.*Mediatype video/.* not supported$",
+        )?);
+    Ok(())
+}
+
+#[test]
+fn test_gen_stdin() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.arg("gen")
+        .arg("--mime")
+        .arg("image/png")
+        .arg("-f")
+        .arg("-")
+        .with_stdin()
+        .path("./tests/test_data/image/demo.png")?
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "CYDfTq7Qc7Fre-CDij3vGU1BkCZ-CRNssh4Qc1x5B",
+        ));
+
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.arg("gen").arg("-f").arg("-");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("--mime <MEDIATYPE>"));
+    Ok(())
+}
+
+#[test]
+fn test_exit_codes() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.args(["info", "CCh2TW8YiFhS0"]);
+    cmd.assert().code(8);
+    let mut unknown = tempfile::Builder::new().suffix(".ext42").tempfile()?;
+    unknown.write_all(&[0, 1, 2, 3])?;
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.arg("gen").arg("-f").arg(unknown.path());
+    cmd.assert()
+        .code(4)
+        .stderr(predicate::str::contains("Unknown file-extension"));
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.args(["gen", "-f", "./tests/test_data/video/master.3gp"]);
+    cmd.assert()
+        .code(5)
+        .stderr(predicate::str::contains("not supported"));
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.args([
+        "--tika-url",
+        "ftp://localhost/tika",
+        "gen",
+        "-f",
+        "demo.txt",
+    ]);
+    cmd.assert()
+        .code(1)
+        .stderr(predicate::str::contains("http:// or https://"));
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.args([
+        "--tika-url",
+        "http://localhost/tika",
+        "--tika-ca-bundle",
+        "./tests/test_data/missing.pem",
+        "gen",
+        "-f",
+        "demo.txt",
+    ]);
+    cmd.assert()
+        .code(1)
+        .stderr(predicate::str::contains("CA bundle"));
+    // a configuration that is fine, but nothing listens there
+    let port = std::net::TcpListener::bind("127.0.0.1:0")?
+        .local_addr()?
+        .port();
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.args([
+        "--tika-url",
+        &format!("http://127.0.0.1:{}", port),
+        "gen",
+        "-f",
+        "demo.txt",
+    ]);
+    cmd.assert()
+        .code(7)
+        .stderr(predicate::str::contains("Apache Tika server unavailable"));
+    Ok(())
+}
+
+#[test]
+fn test_gen_sniffed() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let noext = dir.path().join("upload");
+    std::fs::copy("./tests/test_data/text/demo.docx", &noext)?;
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.arg("gen").arg("-f").arg(&noext);
+    cmd.assert().success().stdout(predicate::str::contains(
+        "CTMjk4o5H96BV-CD6XL9SFyWgsW-CR28vgw3inZGw",
+    ));
+
+    let wrong = dir.path().join("demo.txt");
+    std::fs::copy("./tests/test_data/image/demo.png", &wrong)?;
+    let mut cmd = Command::cargo_bin("iscc-cli")?;
+    cmd.arg("gen").arg("-f").arg(&wrong);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "CYDfTq7Qc7Fre-CDij3vGU1BkCZ-CRNssh4Qc1x5B",
+        ))
+        .stderr(predicate::str::contains(
+            "Content is image/png, but file-extension suggests text/plain",
+        ));
+    Ok(())
+}